@@ -25,6 +25,13 @@ fn compile_tests() {
     t.pass("tests/ui/pass/08-static-env.rs");
     t.pass("tests/ui/pass/09-derive-for-storage.rs");
     t.pass("tests/ui/pass/10-alias-storage-struct-impl.rs");
+    t.pass("tests/ui/pass/11-non-ink-constructor-helper.rs");
+    t.pass("tests/ui/pass/12-nested-storage-struct.rs");
+    t.pass("tests/ui/pass/13-stateless-contract.rs");
+    t.pass("tests/ui/pass/14-multiple-impl-blocks.rs");
+    t.pass("tests/ui/pass/15-collection-fields.rs");
+    t.pass("tests/ui/pass/16-env-accessors.rs");
+    t.pass("tests/ui/pass/17-lazy-computed-getter.rs");
 
     t.compile_fail("tests/ui/fail/C-00-constructor-self-ref.rs");
     t.compile_fail("tests/ui/fail/C-01-constructor-self-mut.rs");
@@ -0,0 +1,41 @@
+use ink_lang as ink;
+
+#[ink::contract]
+mod counter {
+    #[ink(storage)]
+    pub struct Counter {
+        value: i32,
+        step: i32,
+    }
+
+    impl Counter {
+        #[ink(constructor)]
+        pub fn new(init_value: i32) -> Self {
+            Self::with_step(init_value, 1)
+        }
+
+        /// A plain associated constructor that is not dispatchable.
+        pub fn with_step(value: i32, step: i32) -> Self {
+            Self { value, step }
+        }
+
+        #[ink(message)]
+        pub fn inc(&mut self) {
+            self.value += self.step;
+        }
+
+        #[ink(message)]
+        pub fn get(&self) -> i32 {
+            self.value
+        }
+    }
+}
+
+use counter::Counter;
+
+fn main() {
+    let mut counter = Counter::with_step(10, 5);
+    assert_eq!(counter.get(), 10);
+    counter.inc();
+    assert_eq!(counter.get(), 15);
+}
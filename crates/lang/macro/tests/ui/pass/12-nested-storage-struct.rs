@@ -0,0 +1,76 @@
+use ink_lang as ink;
+
+#[ink::contract]
+mod nested_storage {
+    use ink_storage::traits::SpreadLayout;
+
+    /// A group of storage fields that is laid out under the contract root.
+    #[derive(SpreadLayout)]
+    #[cfg_attr(feature = "std", derive(ink_storage::traits::StorageLayout))]
+    pub struct Settings {
+        threshold: u32,
+        enabled: bool,
+    }
+
+    #[ink(storage)]
+    pub struct NestedStorage {
+        value: i32,
+        settings: Settings,
+    }
+
+    impl NestedStorage {
+        #[ink(constructor)]
+        pub fn new(value: i32, threshold: u32) -> Self {
+            Self {
+                value,
+                settings: Settings {
+                    threshold,
+                    enabled: true,
+                },
+            }
+        }
+
+        #[ink(message)]
+        pub fn value(&self) -> i32 {
+            self.value
+        }
+
+        #[ink(message)]
+        pub fn threshold(&self) -> u32 {
+            self.settings.threshold
+        }
+
+        #[ink(message)]
+        pub fn enabled(&self) -> bool {
+            self.settings.enabled
+        }
+    }
+}
+
+use ink_primitives::Key;
+use ink_storage::traits::{
+    pull_spread_root,
+    push_spread_root,
+    SpreadLayout,
+};
+use nested_storage::{
+    NestedStorage,
+    Settings,
+};
+
+fn main() {
+    // One cell for `value` and two cells for the fields of `settings`.
+    assert_eq!(<Settings as SpreadLayout>::FOOTPRINT, 2);
+    assert_eq!(<NestedStorage as SpreadLayout>::FOOTPRINT, 3);
+    ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+        let root_key = Key::from([0x00; 32]);
+        let contract = NestedStorage::new(42, 1337);
+        push_spread_root(&contract, &root_key);
+        let pulled = pull_spread_root::<NestedStorage>(&root_key);
+        assert_eq!(pulled.value(), 42);
+        assert_eq!(pulled.threshold(), 1337);
+        assert!(pulled.enabled());
+        Ok(())
+    })
+    .unwrap()
+}
@@ -0,0 +1,43 @@
+use ink_lang as ink;
+
+#[ink::contract]
+mod calculator {
+    /// A library-style contract that does not hold any storage.
+    #[ink(storage)]
+    pub struct Calculator {}
+
+    impl Calculator {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {}
+        }
+
+        #[ink(message)]
+        pub fn add(&self, a: u32, b: u32) -> u32 {
+            a.saturating_add(b)
+        }
+    }
+}
+
+use calculator::Calculator;
+use ink_primitives::Key;
+use ink_storage::traits::{
+    pull_spread_root,
+    push_spread_root,
+    SpreadLayout,
+};
+
+fn main() {
+    // The contract does not occupy any storage cells.
+    assert_eq!(<Calculator as SpreadLayout>::FOOTPRINT, 0);
+    ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+        let root_key = Key::from([0x00; 32]);
+        let contract = Calculator::new();
+        assert_eq!(contract.add(2, 3), 5);
+        push_spread_root(&contract, &root_key);
+        let pulled = pull_spread_root::<Calculator>(&root_key);
+        assert_eq!(pulled.add(u32::MAX, 1), u32::MAX);
+        Ok(())
+    })
+    .unwrap()
+}
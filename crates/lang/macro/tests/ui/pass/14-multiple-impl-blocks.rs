@@ -0,0 +1,44 @@
+use ink_lang as ink;
+
+#[ink::contract]
+mod counter {
+    #[ink(storage)]
+    pub struct Counter {
+        value: i32,
+    }
+
+    impl Counter {
+        #[ink(constructor)]
+        pub fn new(init_value: i32) -> Self {
+            Self { value: init_value }
+        }
+
+        #[ink(message)]
+        pub fn get(&self) -> i32 {
+            self.value
+        }
+    }
+
+    /// Messages that mutate the counter are grouped in their own block.
+    impl Counter {
+        #[ink(message)]
+        pub fn inc_by(&mut self, by: i32) {
+            self.value += by;
+        }
+
+        #[ink(message)]
+        pub fn reset(&mut self) {
+            self.value = 0;
+        }
+    }
+}
+
+use counter::Counter;
+
+fn main() {
+    let mut counter = Counter::new(5);
+    counter.inc_by(10);
+    assert_eq!(counter.get(), 15);
+    counter.reset();
+    assert_eq!(counter.get(), 0);
+}
@@ -0,0 +1,71 @@
+use ink_lang as ink;
+
+#[ink::contract]
+mod registry {
+    use ink_storage::collections::{
+        HashMap as StorageHashMap,
+        Vec as StorageVec,
+    };
+
+    #[ink(storage)]
+    pub struct Registry {
+        names: StorageHashMap<u32, u64>,
+        history: StorageVec<u32>,
+    }
+
+    impl Registry {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {
+                names: StorageHashMap::new(),
+                history: StorageVec::new(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn register(&mut self, id: u32, value: u64) {
+            self.names.insert(id, value);
+            self.history.push(id);
+        }
+
+        #[ink(message)]
+        pub fn lookup(&self, id: u32) -> Option<u64> {
+            self.names.get(&id).copied()
+        }
+
+        #[ink(message)]
+        pub fn last_registered(&self) -> Option<u32> {
+            self.history.last().copied()
+        }
+    }
+}
+
+use ink_primitives::Key;
+use ink_storage::traits::{
+    pull_spread_root,
+    push_spread_root,
+};
+use registry::Registry;
+
+fn main() {
+    ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+        let root_key = Key::from([0x00; 32]);
+        // Instantiate the contract and register an entry in a first call.
+        let mut contract = Registry::new();
+        contract.register(1, 42);
+        push_spread_root(&contract, &root_key);
+        // Read the entry back in a second call from the pulled storage.
+        let mut contract = pull_spread_root::<Registry>(&root_key);
+        assert_eq!(contract.lookup(1), Some(42));
+        assert_eq!(contract.lookup(2), None);
+        assert_eq!(contract.last_registered(), Some(1));
+        contract.register(2, 1337);
+        push_spread_root(&contract, &root_key);
+        let contract = pull_spread_root::<Registry>(&root_key);
+        assert_eq!(contract.lookup(1), Some(42));
+        assert_eq!(contract.lookup(2), Some(1337));
+        assert_eq!(contract.last_registered(), Some(2));
+        Ok(())
+    })
+    .unwrap()
+}
@@ -0,0 +1,62 @@
+use ink_lang as ink;
+
+#[ink::contract]
+mod env_info {
+    #[ink(storage)]
+    pub struct EnvInfo {}
+
+    impl EnvInfo {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {}
+        }
+
+        #[ink(message)]
+        pub fn caller(&self) -> AccountId {
+            self.env().caller()
+        }
+
+        #[ink(message)]
+        pub fn block_number(&self) -> BlockNumber {
+            self.env().block_number()
+        }
+
+        #[ink(message, payable)]
+        pub fn transferred_balance(&self) -> Balance {
+            self.env().transferred_balance()
+        }
+    }
+}
+
+use env_info::EnvInfo;
+use ink_env::{
+    test,
+    DefaultEnvironment,
+};
+
+fn main() {
+    test::run_test::<DefaultEnvironment, _>(|_| {
+        let contract = EnvInfo::new();
+        let accounts = test::default_accounts::<DefaultEnvironment>()?;
+        // Alice is the caller by default.
+        assert_eq!(contract.caller(), accounts.alice);
+        // Call the contract from Bob while transferring some value.
+        let callee = ink_env::account_id::<DefaultEnvironment>()?;
+        test::push_execution_context::<DefaultEnvironment>(
+            accounts.bob,
+            callee,
+            1_000_000,
+            42,
+            test::CallData::new(ink_env::call::Selector::new([0x00; 4])),
+        );
+        assert_eq!(contract.caller(), accounts.bob);
+        assert_eq!(contract.transferred_balance(), 42);
+        test::pop_execution_context();
+        // The block number is backed by the off-chain environment.
+        let block_number = contract.block_number();
+        test::advance_block::<DefaultEnvironment>()?;
+        assert_eq!(contract.block_number(), block_number + 1);
+        Ok(())
+    })
+    .unwrap()
+}
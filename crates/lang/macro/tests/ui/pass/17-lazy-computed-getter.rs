@@ -0,0 +1,63 @@
+use ink_lang as ink;
+
+#[ink::contract]
+mod lazy_fields {
+    use ink_storage::Lazy;
+
+    #[ink(storage)]
+    pub struct LazyFields {
+        a: Lazy<u32>,
+        b: Lazy<u32>,
+        unused: Lazy<u32>,
+    }
+
+    impl LazyFields {
+        #[ink(constructor)]
+        pub fn new(a: u32, b: u32) -> Self {
+            Self {
+                a: Lazy::new(a),
+                b: Lazy::new(b),
+                unused: Lazy::new(0),
+            }
+        }
+
+        /// Computes a value from two of the three storage fields.
+        #[ink(message)]
+        pub fn sum(&self) -> u32 {
+            *self.a + *self.b
+        }
+    }
+}
+
+use ink_env::{
+    test,
+    DefaultEnvironment,
+};
+use ink_primitives::Key;
+use ink_storage::traits::{
+    pull_spread_root,
+    push_spread_root,
+};
+use lazy_fields::LazyFields;
+
+fn main() {
+    test::run_test::<DefaultEnvironment, _>(|_| {
+        let root_key = Key::from([0x00; 32]);
+        push_spread_root(&LazyFields::new(40, 2), &root_key);
+        let account_id = test::get_current_contract_account_id::<DefaultEnvironment>()?;
+        let (base_reads, _) =
+            test::get_contract_storage_rw::<DefaultEnvironment>(&account_id)?;
+        // Pulling the contract does not load any of its lazy fields.
+        let contract = pull_spread_root::<LazyFields>(&root_key);
+        let (reads, _) =
+            test::get_contract_storage_rw::<DefaultEnvironment>(&account_id)?;
+        assert_eq!(reads - base_reads, 0);
+        // Only the two fields referenced by the message are loaded.
+        assert_eq!(contract.sum(), 42);
+        let (reads, _) =
+            test::get_contract_storage_rw::<DefaultEnvironment>(&account_id)?;
+        assert_eq!(reads - base_reads, 2);
+        Ok(())
+    })
+    .unwrap()
+}
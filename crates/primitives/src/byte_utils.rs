@@ -0,0 +1,213 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Utilities to operate on byte slices as big-endian unsigned integers.
+//!
+//! # Note
+//!
+//! All routines interpret the first byte of a slice as its most significant
+//! byte. This differs from [`Key`](`crate::Key`) whose arithmetic and bit
+//! operations interpret its bytes as a little-endian integer.
+
+/// Shifts the big-endian `bytes` to the left by `n` bits in place.
+///
+/// Bits that are shifted past the most significant end are dropped.
+pub fn shl_bytes(bytes: &mut [u8], n: u32) {
+    let len = bytes.len();
+    let byte_shift = (n / 8) as usize;
+    let bit_shift = n % 8;
+    for i in 0..len {
+        let src = i + byte_shift;
+        let mut byte = 0x00;
+        if src < len {
+            byte = bytes[src] << bit_shift;
+            if bit_shift != 0 && src + 1 < len {
+                byte |= bytes[src + 1] >> (8 - bit_shift);
+            }
+        }
+        bytes[i] = byte;
+    }
+}
+
+/// Shifts the big-endian `bytes` to the right by `n` bits in place.
+///
+/// Bits that are shifted past the least significant end are dropped.
+pub fn shr_bytes(bytes: &mut [u8], n: u32) {
+    let len = bytes.len();
+    let byte_shift = (n / 8) as usize;
+    let bit_shift = n % 8;
+    for i in (0..len).rev() {
+        let mut byte = 0x00;
+        if i >= byte_shift {
+            let src = i - byte_shift;
+            byte = bytes[src] >> bit_shift;
+            if bit_shift != 0 && src > 0 {
+                byte |= bytes[src - 1] << (8 - bit_shift);
+            }
+        }
+        bytes[i] = byte;
+    }
+}
+
+
+/// Adds the big-endian `rhs` bytes to the big-endian `lhs` bytes in place.
+///
+/// Clamps `lhs` to all `0xFF` bytes if the addition overflows.
+/// Returns `true` if clamping occurred.
+///
+/// # Panics
+///
+/// If `lhs` and `rhs` have different lengths.
+pub fn bytes_saturating_add_bytes(lhs: &mut [u8], rhs: &[u8]) -> bool {
+    assert_eq!(lhs.len(), rhs.len(), "encountered byte slices of different lengths");
+    let mut carry = false;
+    for (lhs, rhs) in lhs.iter_mut().zip(rhs).rev() {
+        let (sum, ovfl_a) = lhs.overflowing_add(*rhs);
+        let (sum, ovfl_b) = sum.overflowing_add(carry as u8);
+        *lhs = sum;
+        carry = ovfl_a | ovfl_b;
+    }
+    if carry {
+        lhs.iter_mut().for_each(|byte| *byte = 0xFF);
+    }
+    carry
+}
+
+/// Subtracts the big-endian `rhs` bytes from the big-endian `lhs` bytes in place.
+///
+/// Clamps `lhs` to all `0x00` bytes if the subtraction underflows.
+/// Returns `true` if clamping occurred.
+///
+/// # Panics
+///
+/// If `lhs` and `rhs` have different lengths.
+pub fn bytes_saturating_sub_bytes(lhs: &mut [u8], rhs: &[u8]) -> bool {
+    assert_eq!(lhs.len(), rhs.len(), "encountered byte slices of different lengths");
+    let mut borrow = false;
+    for (lhs, rhs) in lhs.iter_mut().zip(rhs).rev() {
+        let (diff, unfl_a) = lhs.overflowing_sub(*rhs);
+        let (diff, unfl_b) = diff.overflowing_sub(borrow as u8);
+        *lhs = diff;
+        borrow = unfl_a | unfl_b;
+    }
+    if borrow {
+        lhs.iter_mut().for_each(|byte| *byte = 0x00);
+    }
+    borrow
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shl_bytes_works() {
+        let mut bytes = [0x00; 32];
+        bytes[31] = 0x01;
+        shl_bytes(&mut bytes, 8);
+        let mut expected = [0x00; 32];
+        expected[30] = 0x01;
+        assert_eq!(bytes, expected);
+        // Shifting across byte boundaries.
+        let mut bytes = [0x00, 0x81, 0x01];
+        shl_bytes(&mut bytes, 1);
+        assert_eq!(bytes, [0x01, 0x02, 0x02]);
+        // Bits shifted past the most significant end are dropped.
+        let mut bytes = [0x80, 0x00];
+        shl_bytes(&mut bytes, 1);
+        assert_eq!(bytes, [0x00, 0x00]);
+        let mut bytes = [0xFF; 4];
+        shl_bytes(&mut bytes, 32);
+        assert_eq!(bytes, [0x00; 4]);
+    }
+
+    #[test]
+    fn shr_bytes_works() {
+        let mut bytes = [0x00; 32];
+        bytes[0] = 0x80;
+        shr_bytes(&mut bytes, 1);
+        let mut expected = [0x00; 32];
+        expected[0] = 0x40;
+        assert_eq!(bytes, expected);
+        // Shifting across byte boundaries.
+        let mut bytes = [0x01, 0x81, 0x00];
+        shr_bytes(&mut bytes, 1);
+        assert_eq!(bytes, [0x00, 0xC0, 0x80]);
+        // Bits shifted past the least significant end are dropped.
+        let mut bytes = [0x00, 0x01];
+        shr_bytes(&mut bytes, 1);
+        assert_eq!(bytes, [0x00, 0x00]);
+        let mut bytes = [0xFF; 4];
+        shr_bytes(&mut bytes, 32);
+        assert_eq!(bytes, [0x00; 4]);
+    }
+
+    #[test]
+    fn shl_shr_bytes_roundtrip() {
+        let original = [0x12, 0x34, 0x56, 0x78, 0x9A];
+        for n in &[0_u32, 1, 7, 8, 9, 15, 39] {
+            let mut bytes = original;
+            shl_bytes(&mut bytes, *n);
+            shr_bytes(&mut bytes, *n);
+            shl_bytes(&mut bytes, *n);
+            let mut expected = original;
+            shl_bytes(&mut expected, *n);
+            assert_eq!(bytes, expected);
+        }
+    }
+
+    #[test]
+    fn bytes_saturating_add_bytes_works() {
+        // Exact fit: no clamping.
+        let mut lhs = [0x00, 0xFF, 0xFE];
+        assert!(!bytes_saturating_add_bytes(&mut lhs, &[0xFF, 0x00, 0x01]));
+        assert_eq!(lhs, [0xFF, 0xFF, 0xFF]);
+        // Carries propagate towards the most significant byte.
+        let mut lhs = [0x00, 0x00, 0xFF];
+        assert!(!bytes_saturating_add_bytes(&mut lhs, &[0x00, 0x00, 0x01]));
+        assert_eq!(lhs, [0x00, 0x01, 0x00]);
+        // Overflow: clamps to all `0xFF`.
+        let mut lhs = [0xFF, 0xFF, 0xFF];
+        assert!(bytes_saturating_add_bytes(&mut lhs, &[0x00, 0x00, 0x01]));
+        assert_eq!(lhs, [0xFF, 0xFF, 0xFF]);
+        let mut lhs = [0x80, 0x00];
+        assert!(bytes_saturating_add_bytes(&mut lhs, &[0x80, 0x00]));
+        assert_eq!(lhs, [0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn bytes_saturating_sub_bytes_works() {
+        // Exact fit: no clamping.
+        let mut lhs = [0x12, 0x34, 0x56];
+        assert!(!bytes_saturating_sub_bytes(&mut lhs, &[0x12, 0x34, 0x56]));
+        assert_eq!(lhs, [0x00, 0x00, 0x00]);
+        // Borrows propagate towards the most significant byte.
+        let mut lhs = [0x01, 0x00, 0x00];
+        assert!(!bytes_saturating_sub_bytes(&mut lhs, &[0x00, 0x00, 0x01]));
+        assert_eq!(lhs, [0x00, 0xFF, 0xFF]);
+        // Underflow: clamps to all `0x00`.
+        let mut lhs = [0x00, 0x00, 0x01];
+        assert!(bytes_saturating_sub_bytes(&mut lhs, &[0x00, 0x00, 0x02]));
+        assert_eq!(lhs, [0x00, 0x00, 0x00]);
+        let mut lhs = [0x7F, 0xFF];
+        assert!(bytes_saturating_sub_bytes(&mut lhs, &[0x80, 0x00]));
+        assert_eq!(lhs, [0x00, 0x00]);
+    }
+
+    #[test]
+    #[should_panic(expected = "encountered byte slices of different lengths")]
+    fn bytes_saturating_add_bytes_different_lengths_fails() {
+        bytes_saturating_add_bytes(&mut [0x00; 2], &[0x00; 3]);
+    }
+}
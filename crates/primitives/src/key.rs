@@ -13,12 +13,27 @@
 // limitations under the License.
 
 use core::{
+    cmp::Ordering,
+    convert::TryFrom,
     fmt,
     ops::{
         Add,
         AddAssign,
+        Shl,
+        ShlAssign,
+        Shr,
+        ShrAssign,
+        Sub,
     },
 };
+use ink_prelude::{
+    string::String,
+    vec::Vec,
+};
+use tiny_keccak::{
+    Hasher,
+    Keccak,
+};
 
 /// Key into contract storage.
 ///
@@ -29,6 +44,16 @@ use core::{
 ///
 /// This is the most low-level primitive to identify contract storage cells.
 ///
+/// # Ordering
+///
+/// Arithmetic and bit operations such as shifts, [`Key::mask_prefix`] or
+/// [`Key::leading_zeros`] interpret the key as a 256-bit little-endian integer
+/// whose most significant byte is the last byte of [`Key::to_bytes`].
+/// The `Ord` implementation of `Key` instead compares the `u64` limbs of the key
+/// lexicographically starting with the least significant limb and therefore is
+/// not numeric. Use [`Key::to_int`] in order to compare keys numerically.
+/// For big-endian shifts of raw bytes see [`byte_utils`](`crate::byte_utils`).
+///
 /// # Unsafe
 ///
 /// Prefer using high-level types found in `ink_storage` to operate on the contract
@@ -38,6 +63,12 @@ use core::{
 pub struct Key([u64; 4]);
 
 impl Key {
+    /// The key with all bits set to zero.
+    pub const ZERO: Key = Key([0x00; 4]);
+
+    /// The key with all bits set to one.
+    pub const MAX: Key = Key([u64::MAX; 4]);
+
     fn write_bytes(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "0x")?;
         for limb in &self.0 {
@@ -85,6 +116,49 @@ impl From<[u8; 32]> for Key {
     }
 }
 
+/// Error returned when converting a byte slice with a length other than 32
+/// into a [`Key`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KeyLengthError;
+
+impl fmt::Display for KeyLengthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "encountered byte slice with invalid length for a key")
+    }
+}
+
+/// Error returned when parsing a [`Key`] from a string failed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyParseError {
+    /// The string contains a character that is not part of the alphabet.
+    InvalidCharacter,
+    /// The string does not represent exactly 32 bytes.
+    InvalidLength,
+}
+
+impl fmt::Display for KeyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidCharacter => {
+                write!(f, "encountered invalid character for a key")
+            }
+            Self::InvalidLength => write!(f, "encountered invalid length for a key"),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Key {
+    type Error = KeyLengthError;
+
+    /// Creates a key from the given bytes if there are exactly 32 of them.
+    #[inline]
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        <[u8; 32]>::try_from(bytes)
+            .map(Key::from)
+            .map_err(|_| KeyLengthError)
+    }
+}
+
 impl Key {
     /// Creates a new key from the given bytes.
     ///
@@ -193,6 +267,210 @@ impl Key {
     }
 }
 
+impl Key {
+    /// Creates a key whose byte representation starts with the little-endian
+    /// bytes of `value` followed by zeros.
+    ///
+    /// This is the key that is yielded by adding `value` to the default key.
+    #[inline]
+    pub fn from_u64_le(value: u64) -> Self {
+        Self([value, 0x00, 0x00, 0x00])
+    }
+
+    /// Creates a key whose byte representation ends with the big-endian
+    /// bytes of `value` preceded by zeros.
+    #[inline]
+    pub fn from_u64_be(value: u64) -> Self {
+        Self([0x00, 0x00, 0x00, value.swap_bytes()])
+    }
+
+    /// Returns the `u64` encoded in little-endian order at the start of the key.
+    ///
+    /// Returns `None` if any of the remaining bytes is non-zero.
+    #[inline]
+    pub fn to_u64_le(&self) -> Option<u64> {
+        match self.0 {
+            [value, 0x00, 0x00, 0x00] => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the `u64` encoded in big-endian order at the end of the key.
+    ///
+    /// Returns `None` if any of the preceding bytes is non-zero.
+    #[inline]
+    pub fn to_u64_be(&self) -> Option<u64> {
+        match self.0 {
+            [0x00, 0x00, 0x00, value] => Some(value.swap_bytes()),
+            _ => None,
+        }
+    }
+}
+
+impl Key {
+    /// Interleaves the bits of the `x` and `y` coordinates into a key.
+    ///
+    /// The bits of `x` are placed at the even and the bits of `y` at the odd
+    /// bit positions of the lower 16 bytes of the key (Morton or Z-order).
+    /// This preserves spatial locality of two dimensional coordinates in the
+    /// key space. The upper 16 bytes of the key are zero.
+    pub fn morton_encode(x: u64, y: u64) -> Self {
+        let interleaved = spread_bits(x) | (spread_bits(y) << 1);
+        Self([interleaved as u64, (interleaved >> 64) as u64, 0x00, 0x00])
+    }
+
+    /// Returns the `(x, y)` coordinates that are interleaved in the key.
+    ///
+    /// This is the inverse of [`Key::morton_encode`] and ignores the upper
+    /// 16 bytes of the key.
+    pub fn morton_decode(&self) -> (u64, u64) {
+        let interleaved = (self.0[0] as u128) | ((self.0[1] as u128) << 64);
+        (compact_bits(interleaved), compact_bits(interleaved >> 1))
+    }
+}
+
+/// Spreads the bits of `value` so that they occupy the even bit positions.
+#[rustfmt::skip]
+fn spread_bits(value: u64) -> u128 {
+    let mut x = value as u128;
+    x = (x | (x << 32)) & 0x0000_0000_FFFF_FFFF_0000_0000_FFFF_FFFF;
+    x = (x | (x << 16)) & 0x0000_FFFF_0000_FFFF_0000_FFFF_0000_FFFF;
+    x = (x | (x <<  8)) & 0x00FF_00FF_00FF_00FF_00FF_00FF_00FF_00FF;
+    x = (x | (x <<  4)) & 0x0F0F_0F0F_0F0F_0F0F_0F0F_0F0F_0F0F_0F0F;
+    x = (x | (x <<  2)) & 0x3333_3333_3333_3333_3333_3333_3333_3333;
+    x = (x | (x <<  1)) & 0x5555_5555_5555_5555_5555_5555_5555_5555;
+    x
+}
+
+/// Collects the even bits of `value` into a `u64`.
+///
+/// This is the inverse of [`spread_bits`].
+#[rustfmt::skip]
+fn compact_bits(value: u128) -> u64 {
+    let mut x = value & 0x5555_5555_5555_5555_5555_5555_5555_5555;
+    x = (x | (x >>  1)) & 0x3333_3333_3333_3333_3333_3333_3333_3333;
+    x = (x | (x >>  2)) & 0x0F0F_0F0F_0F0F_0F0F_0F0F_0F0F_0F0F_0F0F;
+    x = (x | (x >>  4)) & 0x00FF_00FF_00FF_00FF_00FF_00FF_00FF_00FF;
+    x = (x | (x >>  8)) & 0x0000_FFFF_0000_FFFF_0000_FFFF_0000_FFFF;
+    x = (x | (x >> 16)) & 0x0000_0000_FFFF_FFFF_0000_0000_FFFF_FFFF;
+    x = (x | (x >> 32)) & 0x0000_0000_0000_0000_FFFF_FFFF_FFFF_FFFF;
+    x as u64
+}
+
+impl Key {
+    /// Returns the key of the `i`-th probe in a deterministic probing sequence.
+    ///
+    /// The probe keys are derived by mixing the bits of `self` with the probe
+    /// index without using a cryptographic hash function. The 0-th probe is
+    /// `self` and all further probes for the same key are distinct.
+    ///
+    /// # Note
+    ///
+    /// Useful for open-addressing style collections operating on the contract
+    /// storage. The derived keys are well distributed but not collision resistant.
+    pub fn next_probe(&self, i: u32) -> Key {
+        if i == 0 {
+            return *self
+        }
+        let seed = (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        let mut result = self.0;
+        for (n, limb) in result.iter_mut().enumerate() {
+            *limb = mix_bits(*limb ^ seed.wrapping_add(n as u64));
+        }
+        Key(result)
+    }
+}
+
+/// Mixes the bits of `value` using the bijective finalizer of SplitMix64.
+fn mix_bits(value: u64) -> u64 {
+    let mut z = value;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl Key {
+    /// Returns the index of the bucket out of `n` buckets the key belongs to.
+    ///
+    /// The index is computed by folding the key into a `u64` via XOR and
+    /// taking the result modulo `n`.
+    ///
+    /// # Panics
+    ///
+    /// If `n` is zero.
+    pub fn bucket(&self, n: u64) -> u64 {
+        assert!(n != 0, "encountered zero buckets");
+        let folded = self.0.iter().fold(0, |acc, limb| acc ^ limb);
+        folded % n
+    }
+}
+
+impl Key {
+    /// Restricts the key to the inclusive `[min, max]` range of keys.
+    ///
+    /// Returns `min` if `self` is less than `min` and `max` if `self` is
+    /// greater than `max`. Otherwise returns `self`.
+    ///
+    /// # Note
+    ///
+    /// Keys are compared numerically as by [`Key::to_int`] and not by the
+    /// limb-wise `Ord` implementation of `Key`.
+    ///
+    /// # Panics
+    ///
+    /// If `min` is greater than `max`.
+    pub fn clamp_key(self, min: Key, max: Key) -> Key {
+        let (value, lower, upper) = (self.to_int(), min.to_int(), max.to_int());
+        assert!(lower <= upper, "encountered invalid key range: min > max");
+        if value < lower {
+            min
+        } else if value > upper {
+            max
+        } else {
+            self
+        }
+    }
+}
+
+impl Key {
+    /// Returns `true` if both keys are equal, in constant time.
+    ///
+    /// # Note
+    ///
+    /// Unlike `==` this always inspects all bytes of both keys and thus does
+    /// not leak the position of the first differing byte through timing.
+    /// Use this for keys that have been derived from secrets.
+    pub fn ct_eq(&self, other: &Key) -> bool {
+        let diff = self
+            .0
+            .iter()
+            .zip(other.0.iter())
+            .fold(0_u64, |acc, (lhs, rhs)| acc | (lhs ^ rhs));
+        diff == 0
+    }
+}
+
+impl Key {
+    /// Compares the bytes of both keys cyclically starting at `start_byte`.
+    ///
+    /// Both keys are compared byte by byte starting at the byte at `start_byte`,
+    /// wrapping around after the last byte. This allows for ring orders where
+    /// the origin of the comparison moves, e.g. for consistent hashing.
+    ///
+    /// # Note
+    ///
+    /// `start_byte` is taken modulo 32 and a `start_byte` of zero compares
+    /// the bytes as returned by [`Key::to_bytes`] lexicographically.
+    pub fn cmp_from(&self, other: &Key, start_byte: usize) -> Ordering {
+        let lhs = self.to_bytes();
+        let rhs = other.to_bytes();
+        let start = start_byte % 32;
+        let lhs_ring = lhs[start..].iter().chain(lhs[..start].iter());
+        let rhs_ring = rhs[start..].iter().chain(rhs[..start].iter());
+        lhs_ring.cmp(rhs_ring)
+    }
+}
+
 impl Add<u64> for Key {
     type Output = Key;
 
@@ -246,150 +524,1436 @@ const _: () = {
 
 impl AddAssign<u64> for Key {
     #[inline]
-    #[rustfmt::skip]
     fn add_assign(&mut self, rhs: u64) {
-        let (res_0,  ovfl_0) = self.0[0].overflowing_add(rhs);
-        let (res_1,  ovfl_1) = self.0[1].overflowing_add(ovfl_0 as u64);
-        let (res_2,  ovfl_2) = self.0[2].overflowing_add(ovfl_1 as u64);
-        let (res_3, _ovfl_3) = self.0[3].overflowing_add(ovfl_2 as u64);
-        self.0[0] = res_0;
-        self.0[1] = res_1;
-        self.0[2] = res_2;
-        self.0[3] = res_3;
+        *self = self.overflowing_add(rhs).0;
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl Key {
+    /// Adds `rhs` to the key and returns the result and whether an arithmetic
+    /// overflow occurred.
+    ///
+    /// Upon overflow the result wraps around.
+    #[inline]
+    pub fn overflowing_add(self, rhs: u64) -> (Key, bool) {
+        let (res_0, ovfl_0) = self.0[0].overflowing_add(rhs);
+        let (res_1, ovfl_1) = self.0[1].overflowing_add(ovfl_0 as u64);
+        let (res_2, ovfl_2) = self.0[2].overflowing_add(ovfl_1 as u64);
+        let (res_3, ovfl_3) = self.0[3].overflowing_add(ovfl_2 as u64);
+        (Key([res_0, res_1, res_2, res_3]), ovfl_3)
+    }
 
-    fn test_bytes() -> [u8; 32] {
-        *b"\
-            \x00\x01\x02\x03\x04\x05\x06\x07\
-            \x08\x09\x0A\x0B\x0C\x0D\x0E\x0F\
-            \x10\x11\x12\x13\x14\x15\x16\x17\
-            \x18\x19\x1A\x1B\x1C\x1D\x1E\x1F\
-        "
+    /// Subtracts `rhs` from the key and returns the result and whether an
+    /// arithmetic underflow occurred.
+    ///
+    /// Upon underflow the result wraps around.
+    #[inline]
+    pub fn overflowing_sub(self, rhs: u64) -> (Key, bool) {
+        let (res_0, unfl_0) = self.0[0].overflowing_sub(rhs);
+        let (res_1, unfl_1) = self.0[1].overflowing_sub(unfl_0 as u64);
+        let (res_2, unfl_2) = self.0[2].overflowing_sub(unfl_1 as u64);
+        let (res_3, unfl_3) = self.0[3].overflowing_sub(unfl_2 as u64);
+        (Key([res_0, res_1, res_2, res_3]), unfl_3)
     }
 
-    #[test]
-    fn default_works() {
-        assert_eq!(<Key as Default>::default().to_bytes(), [0x00; 32]);
+    /// Adds `rhs` to the key saturating at the maximum key upon overflow.
+    #[inline]
+    pub fn saturating_add(self, rhs: u64) -> Key {
+        match self.overflowing_add(rhs) {
+            (result, false) => result,
+            (_, true) => Key::MAX,
+        }
     }
 
-    #[test]
-    fn debug_works() {
-        let key = Key::from(test_bytes());
-        assert_eq!(
-            format!("{:?}", key),
-            String::from(
-                "Key(0x\
-                    _0001020304050607\
-                    _08090A0B0C0D0E0F\
-                    _1011121314151617\
-                    _18191A1B1C1D1E1F\
-                )"
-            ),
-        );
+    /// Subtracts `rhs` from the key saturating at the zero key upon underflow.
+    #[inline]
+    pub fn saturating_sub(self, rhs: u64) -> Key {
+        match self.overflowing_sub(rhs) {
+            (result, false) => result,
+            (_, true) => Key::ZERO,
+        }
     }
 
-    #[test]
-    #[rustfmt::skip]
-    fn from_works() {
-        let test_bytes = test_bytes();
-        assert_eq!(Key::from(test_bytes).to_bytes(), test_bytes);
-        assert_eq!(Key::from_bytes_be_fallback(test_bytes).to_bytes(), test_bytes);
-        assert_eq!(Key::from(test_bytes).to_bytes_be_fallback(), test_bytes);
-        assert_eq!(Key::from_bytes_be_fallback(test_bytes).to_bytes_be_fallback(), test_bytes);
+    /// Adds `rhs` to the key and returns `None` upon overflow.
+    #[inline]
+    pub fn checked_add(self, rhs: u64) -> Option<Key> {
+        match self.overflowing_add(rhs) {
+            (result, false) => Some(result),
+            (_, true) => None,
+        }
     }
 
-    #[test]
-    fn add_one_to_zero() {
-        let bytes = [0x00; 32];
-        let expected = {
-            let mut bytes = [0x00; 32];
-            bytes[0] = 0x01;
-            bytes
-        };
-        let mut key = Key::from(bytes);
-        key.add_assign(1u64);
-        assert_eq!(key.to_bytes(), expected);
+    /// Subtracts `rhs` from the key and returns `None` upon underflow.
+    #[inline]
+    pub fn checked_sub(self, rhs: u64) -> Option<Key> {
+        match self.overflowing_sub(rhs) {
+            (result, false) => Some(result),
+            (_, true) => None,
+        }
     }
 
-    #[test]
-    fn add_with_ovfl() {
-        let bytes = *b"\
-            \xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\
-            \x00\x00\x00\x00\x00\x00\x00\x00\
-            \x00\x00\x00\x00\x00\x00\x00\x00\
-            \x00\x00\x00\x00\x00\x00\x00\x00\
-        ";
-        let expected = {
-            let mut expected = [0x00; 32];
-            expected[8] = 0x01;
-            expected
-        };
-        let mut key = Key::from(bytes);
-        key.add_assign(1u64);
-        assert_eq!(key.to_bytes(), expected);
+    /// Returns the key that directly follows `self` or `None` for [`Key::MAX`].
+    #[inline]
+    pub fn next(self) -> Option<Key> {
+        self.checked_add(1)
     }
 
-    #[test]
-    fn add_with_ovfl_2() {
-        let bytes = *b"\
-            \xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\
-            \xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\
-            \x00\x00\x00\x00\x00\x00\x00\x00\
-            \x00\x00\x00\x00\x00\x00\x00\x00\
-        ";
-        let expected = {
-            let mut expected = [0x00; 32];
-            expected[16] = 0x01;
-            expected
-        };
-        let mut key = Key::from(bytes);
-        key.add_assign(1u64);
-        assert_eq!(key.to_bytes(), expected);
+    /// Returns the key that directly precedes `self` or `None` for [`Key::ZERO`].
+    #[inline]
+    pub fn prev(self) -> Option<Key> {
+        self.checked_sub(1)
     }
 
-    #[test]
-    fn add_with_ovfl_3() {
-        let bytes = *b"\
-            \xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\
-            \xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\
-            \xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\
-            \x00\x00\x00\x00\x00\x00\x00\x00\
-        ";
-        let expected = {
-            let mut expected = [0x00; 32];
-            expected[24] = 0x01;
-            expected
+    /// Adds `rhs` to the key wrapping around at the boundary of the key space.
+    ///
+    /// Accepts any unsigned integer up to `u128`.
+    #[inline]
+    pub fn wrapping_add<T>(self, rhs: T) -> Key
+    where
+        T: Into<u128>,
+    {
+        let rhs = rhs.into();
+        let (res_0, ovfl_0) = self.0[0].overflowing_add(rhs as u64);
+        let (res_1, ovfl_1a) = self.0[1].overflowing_add((rhs >> 64) as u64);
+        let (res_1, ovfl_1b) = res_1.overflowing_add(ovfl_0 as u64);
+        let (res_2, ovfl_2) = self.0[2].overflowing_add((ovfl_1a | ovfl_1b) as u64);
+        let res_3 = self.0[3].wrapping_add(ovfl_2 as u64);
+        Key([res_0, res_1, res_2, res_3])
+    }
+
+    /// Subtracts `rhs` from the key wrapping around at the boundary of the key space.
+    ///
+    /// Accepts any unsigned integer up to `u128`.
+    #[inline]
+    pub fn wrapping_sub<T>(self, rhs: T) -> Key
+    where
+        T: Into<u128>,
+    {
+        let rhs = rhs.into();
+        let (res_0, unfl_0) = self.0[0].overflowing_sub(rhs as u64);
+        let (res_1, unfl_1a) = self.0[1].overflowing_sub((rhs >> 64) as u64);
+        let (res_1, unfl_1b) = res_1.overflowing_sub(unfl_0 as u64);
+        let (res_2, unfl_2) = self.0[2].overflowing_sub((unfl_1a | unfl_1b) as u64);
+        let res_3 = self.0[3].wrapping_sub(unfl_2 as u64);
+        Key([res_0, res_1, res_2, res_3])
+    }
+}
+
+impl Key {
+    /// Returns an iterator over `n` keys that are evenly spaced clockwise
+    /// around the key space starting after `self`.
+    ///
+    /// The key space is treated as a ring that wraps around from [`Key::MAX`]
+    /// to [`Key::ZERO`] and is split into `n + 1` equally sized arcs, one of
+    /// which starts at `self`. This is useful for replica placement in
+    /// consistent hashing.
+    pub fn ring_successors(self, n: usize) -> impl Iterator<Item = Key> {
+        let arcs = (n as u64).saturating_add(1);
+        // The size of the key space is `Key::MAX + 1` which we cannot represent.
+        let (quotient, remainder) = Key::MAX.to_int().div_rem_small(arcs);
+        let step = if remainder == arcs - 1 {
+            quotient + KeyInt::from(1)
+        } else {
+            quotient
         };
-        let mut key = Key::from(bytes);
-        key.add_assign(1u64);
-        assert_eq!(key.to_bytes(), expected);
+        (0..n).scan(self.to_int(), move |key, _| {
+            *key = *key + step;
+            Some(key.to_key())
+        })
     }
+}
 
-    #[test]
-    fn add_with_wrap() {
-        let bytes = [0xFF; 32];
-        let expected = [0x00; 32];
-        let mut key = Key::from(bytes);
-        key.add_assign(1u64);
-        assert_eq!(key.to_bytes(), expected);
+impl ShlAssign<u32> for Key {
+    /// Shifts the key to the left by `rhs` bits.
+    ///
+    /// Bits that are shifted past the most significant end are dropped.
+    #[inline]
+    fn shl_assign(&mut self, rhs: u32) {
+        if rhs >= 256 {
+            *self = Key::default();
+            return
+        }
+        let limb_shift = (rhs / 64) as usize;
+        let bit_shift = rhs % 64;
+        let mut result = [0x00_u64; 4];
+        for (i, limb) in result.iter_mut().enumerate().skip(limb_shift) {
+            let src = i - limb_shift;
+            *limb = self.0[src] << bit_shift;
+            if bit_shift != 0 && src > 0 {
+                *limb |= self.0[src - 1] >> (64 - bit_shift);
+            }
+        }
+        self.0 = result;
     }
+}
 
-    #[test]
-    fn add_assign_to_zero() {
-        for test_value in &[0_u64, 1, 42, 10_000, u32::MAX as u64, u64::MAX] {
-            let mut key = <Key as Default>::default();
-            let expected = {
-                let mut expected = [0x00; 32];
-                expected[0..8].copy_from_slice(&test_value.to_le_bytes());
-                expected
-            };
-            key.add_assign(*test_value);
-            assert_eq!(key.to_bytes(), expected);
+impl ShrAssign<u32> for Key {
+    /// Shifts the key to the right by `rhs` bits.
+    ///
+    /// Bits that are shifted past the least significant end are dropped.
+    #[inline]
+    fn shr_assign(&mut self, rhs: u32) {
+        if rhs >= 256 {
+            *self = Key::default();
+            return
+        }
+        let limb_shift = (rhs / 64) as usize;
+        let bit_shift = rhs % 64;
+        let mut result = [0x00_u64; 4];
+        for (i, limb) in result.iter_mut().enumerate().take(4 - limb_shift) {
+            let src = i + limb_shift;
+            *limb = self.0[src] >> bit_shift;
+            if bit_shift != 0 && src + 1 < 4 {
+                *limb |= self.0[src + 1] << (64 - bit_shift);
+            }
         }
+        self.0 = result;
+    }
+}
+
+impl Key {
+    /// Rotates the bits of the key to the left by `n` bits.
+    ///
+    /// Bits that are shifted past the most significant end wrap around
+    /// to the least significant end.
+    ///
+    /// # Note
+    ///
+    /// The key is interpreted as a 256-bit little-endian integer so that its
+    /// most significant bit is the highest bit of its last byte. Therefore
+    /// rotating a key whose first byte is `0x80` to the left by one bit moves
+    /// that bit into the second byte and not around into the last byte.
+    pub fn rotate_left(self, n: u32) -> Key {
+        let n = n % 256;
+        if n == 0 {
+            return self
+        }
+        let high = self << n;
+        let low = self >> (256 - n);
+        Key([
+            high.0[0] | low.0[0],
+            high.0[1] | low.0[1],
+            high.0[2] | low.0[2],
+            high.0[3] | low.0[3],
+        ])
+    }
+
+    /// Rotates the bits of the key to the right by `n` bits.
+    ///
+    /// Bits that are shifted past the least significant end wrap around
+    /// to the most significant end.
+    ///
+    /// # Note
+    ///
+    /// Uses the same little-endian bit numbering as [`Key::rotate_left`].
+    pub fn rotate_right(self, n: u32) -> Key {
+        self.rotate_left(256 - n % 256)
+    }
+}
+
+impl Key {
+    /// Keeps the `bits` most significant bits of the key and zeros all others.
+    ///
+    /// Keys that share the same prefix of `bits` bits are masked to the same key.
+    /// Returns [`Key::ZERO`] if `bits` is zero and `self` if `bits` is 256 or more.
+    pub fn mask_prefix(self, bits: u32) -> Key {
+        if bits == 0 {
+            return Key::ZERO
+        }
+        if bits >= 256 {
+            return self
+        }
+        let shift = 256 - bits;
+        (self >> shift) << shift
+    }
+}
+
+impl Key {
+    /// Returns the bitwise exclusive or of both keys.
+    fn xor(self, rhs: Key) -> Key {
+        Key([
+            self.0[0] ^ rhs.0[0],
+            self.0[1] ^ rhs.0[1],
+            self.0[2] ^ rhs.0[2],
+            self.0[3] ^ rhs.0[3],
+        ])
+    }
+
+    /// Converts the key into its binary-reflected Gray code.
+    ///
+    /// Keys of consecutive integers are converted into keys that differ in
+    /// exactly one bit.
+    pub fn to_gray(self) -> Key {
+        self.xor(self >> 1)
+    }
+
+    /// Converts the binary-reflected Gray code back into the original key.
+    ///
+    /// This is the inverse of [`Key::to_gray`].
+    pub fn from_gray(self) -> Key {
+        let mut result = self;
+        let mut shift = 1;
+        while shift < 256 {
+            result = result.xor(result >> shift);
+            shift <<= 1;
+        }
+        result
+    }
+}
+
+impl Key {
+    /// Returns the number of ones in the binary representation of the key.
+    pub fn count_ones(&self) -> u32 {
+        self.0.iter().map(|limb| limb.count_ones()).sum()
+    }
+
+    /// Returns the number of zeros in the binary representation of the key.
+    pub fn count_zeros(&self) -> u32 {
+        256 - self.count_ones()
+    }
+
+    /// Returns the number of leading zeros in the binary representation of the key.
+    ///
+    /// # Note
+    ///
+    /// The key is interpreted as a 256-bit little-endian integer so that its
+    /// most significant bit is the highest bit of its last byte. Therefore a
+    /// key whose bytes are all zero except for a last byte of `0x01` has 7
+    /// leading zeros and not 255 as it would with big-endian bit numbering.
+    pub fn leading_zeros(&self) -> u32 {
+        let mut zeros = 0;
+        for limb in self.0.iter().rev() {
+            zeros += limb.leading_zeros();
+            if *limb != 0 {
+                break
+            }
+        }
+        zeros
+    }
+}
+
+impl Shl<u32> for Key {
+    type Output = Key;
+
+    fn shl(mut self, rhs: u32) -> Self::Output {
+        self <<= rhs;
+        self
+    }
+}
+
+impl Shr<u32> for Key {
+    type Output = Key;
+
+    fn shr(mut self, rhs: u32) -> Self::Output {
+        self >>= rhs;
+        self
+    }
+}
+
+/// A 256-bit unsigned integer with the same representation as a [`Key`].
+///
+/// # Note
+///
+/// Unlike [`Key`] the ordering of `KeyInt` is numeric.
+/// All arithmetic operations wrap around at the boundary of the 256-bit range.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct KeyInt([u64; 4]);
+
+impl From<u64> for KeyInt {
+    fn from(value: u64) -> Self {
+        Self([value, 0, 0, 0])
+    }
+}
+
+impl Key {
+    /// Returns the key interpreted as a 256-bit little-endian unsigned integer.
+    pub fn to_int(&self) -> KeyInt {
+        KeyInt(self.0)
+    }
+}
+
+impl KeyInt {
+    /// Returns the key with the same bytes as the integer.
+    pub fn to_key(&self) -> Key {
+        Key(self.0)
+    }
+
+    /// Multiplies the integer with `rhs` wrapping around at the boundary.
+    pub fn mul_small(self, rhs: u64) -> KeyInt {
+        let mut result = [0x00; 4];
+        let mut carry = 0_u128;
+        for (limb, lhs) in result.iter_mut().zip(self.0.iter()) {
+            let product = (*lhs as u128) * (rhs as u128) + carry;
+            *limb = product as u64;
+            carry = product >> 64;
+        }
+        KeyInt(result)
+    }
+
+    /// Divides the integer by `rhs` and returns the quotient and the remainder.
+    ///
+    /// # Panics
+    ///
+    /// If `rhs` is zero.
+    pub fn div_rem_small(self, rhs: u64) -> (KeyInt, u64) {
+        let rhs = rhs as u128;
+        let mut quotient = [0x00; 4];
+        let mut remainder = 0_u128;
+        for (limb, result) in self.0.iter().zip(quotient.iter_mut()).rev() {
+            let current = (remainder << 64) | *limb as u128;
+            *result = (current / rhs) as u64;
+            remainder = current % rhs;
+        }
+        (KeyInt(quotient), remainder as u64)
+    }
+}
+
+impl KeyInt {
+    /// Adds `rhs` to the integer.
+    ///
+    /// Returns the wrapped result and `true` if an overflow occurred.
+    pub fn overflowing_add(self, rhs: KeyInt) -> (KeyInt, bool) {
+        let mut result = [0x00; 4];
+        let mut carry = false;
+        for (i, limb) in result.iter_mut().enumerate() {
+            let (sum, ovfl_a) = self.0[i].overflowing_add(rhs.0[i]);
+            let (sum, ovfl_b) = sum.overflowing_add(carry as u64);
+            *limb = sum;
+            carry = ovfl_a | ovfl_b;
+        }
+        (KeyInt(result), carry)
+    }
+
+    /// Subtracts `rhs` from the integer.
+    ///
+    /// Returns the wrapped result and `true` if an underflow occurred.
+    pub fn overflowing_sub(self, rhs: KeyInt) -> (KeyInt, bool) {
+        let mut result = [0x00; 4];
+        let mut borrow = false;
+        for (i, limb) in result.iter_mut().enumerate() {
+            let (diff, unfl_a) = self.0[i].overflowing_sub(rhs.0[i]);
+            let (diff, unfl_b) = diff.overflowing_sub(borrow as u64);
+            *limb = diff;
+            borrow = unfl_a | unfl_b;
+        }
+        (KeyInt(result), borrow)
+    }
+}
+
+impl Add for KeyInt {
+    type Output = KeyInt;
+
+    fn add(self, rhs: KeyInt) -> Self::Output {
+        self.overflowing_add(rhs).0
+    }
+}
+
+impl Sub for KeyInt {
+    type Output = KeyInt;
+
+    fn sub(self, rhs: KeyInt) -> Self::Output {
+        self.overflowing_sub(rhs).0
+    }
+}
+
+impl PartialOrd for KeyInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for KeyInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Compare starting at the most significant limb.
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+/// The Bitcoin base58 alphabet.
+const BASE58_ALPHABET: &[u8; 58] =
+    b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+impl Key {
+    /// Returns the base58 encoding of the bytes of the key.
+    ///
+    /// # Note
+    ///
+    /// The bytes are encoded in the order returned by [`Key::to_bytes`] using
+    /// the Bitcoin alphabet. Mainly useful for compact display of keys in logs.
+    pub fn to_base58(&self) -> String {
+        let bytes = self.to_bytes();
+        let zeros = bytes.iter().take_while(|&&byte| byte == 0x00).count();
+        // The base58 digits in little-endian order.
+        let mut digits = Vec::with_capacity(44);
+        for &byte in &bytes[zeros..] {
+            let mut carry = byte as u32;
+            for digit in digits.iter_mut() {
+                carry += (*digit as u32) << 8;
+                *digit = (carry % 58) as u8;
+                carry /= 58;
+            }
+            while carry > 0 {
+                digits.push((carry % 58) as u8);
+                carry /= 58;
+            }
+        }
+        let mut encoded = String::with_capacity(zeros + digits.len());
+        for _ in 0..zeros {
+            encoded.push('1');
+        }
+        encoded.extend(
+            digits
+                .iter()
+                .rev()
+                .map(|&digit| BASE58_ALPHABET[digit as usize] as char),
+        );
+        encoded
+    }
+
+    /// Parses a key from the base58 encoding of its bytes.
+    ///
+    /// This is the inverse of [`Key::to_base58`].
+    ///
+    /// # Errors
+    ///
+    /// - If the string contains characters outside of the base58 alphabet.
+    /// - If the string does not decode to exactly 32 bytes.
+    pub fn from_base58(encoded: &str) -> Result<Key, KeyParseError> {
+        let zeros = encoded.bytes().take_while(|&byte| byte == b'1').count();
+        // The decoded bytes in little-endian order.
+        let mut decoded = Vec::with_capacity(32);
+        for byte in encoded.bytes().skip(zeros) {
+            let mut carry = BASE58_ALPHABET
+                .iter()
+                .position(|&symbol| symbol == byte)
+                .ok_or(KeyParseError::InvalidCharacter)?
+                as u32;
+            for decoded_byte in decoded.iter_mut() {
+                carry += (*decoded_byte as u32) * 58;
+                *decoded_byte = carry as u8;
+                carry >>= 8;
+            }
+            while carry > 0 {
+                decoded.push(carry as u8);
+                carry >>= 8;
+            }
+            if zeros + decoded.len() > 32 {
+                return Err(KeyParseError::InvalidLength)
+            }
+        }
+        if zeros + decoded.len() != 32 {
+            return Err(KeyParseError::InvalidLength)
+        }
+        let mut bytes = [0x00; 32];
+        for (byte, decoded_byte) in bytes[zeros..].iter_mut().zip(decoded.iter().rev()) {
+            *byte = *decoded_byte;
+        }
+        Ok(Key::from(bytes))
+    }
+}
+
+impl Key {
+    /// Returns the `index`-th child key of `self` in a hierarchical key namespace.
+    ///
+    /// The child key is the Keccak-256 hash of the bytes of `self` followed by
+    /// the little-endian bytes of `index`.
+    ///
+    /// # Note
+    ///
+    /// Useful to derive disjoint storage regions for nested data structures
+    /// from a single root key.
+    pub fn child(&self, index: u32) -> Key {
+        let mut hasher = Keccak::v256();
+        hasher.update(&self.to_bytes());
+        hasher.update(&index.to_le_bytes());
+        let mut output = [0x00; 32];
+        hasher.finalize(&mut output);
+        Key::from(output)
+    }
+
+    /// Returns the key at the end of `path` starting from `self`.
+    ///
+    /// This is equal to successively deriving the [`Key::child`] keys for all
+    /// indices in `path`. An empty path yields `self`.
+    pub fn derive_path(&self, path: &[u32]) -> Key {
+        path.iter().fold(*self, |key, &index| key.child(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_bytes() -> [u8; 32] {
+        *b"\
+            \x00\x01\x02\x03\x04\x05\x06\x07\
+            \x08\x09\x0A\x0B\x0C\x0D\x0E\x0F\
+            \x10\x11\x12\x13\x14\x15\x16\x17\
+            \x18\x19\x1A\x1B\x1C\x1D\x1E\x1F\
+        "
+    }
+
+    #[test]
+    fn default_works() {
+        assert_eq!(<Key as Default>::default().to_bytes(), [0x00; 32]);
+    }
+
+    #[test]
+    fn debug_works() {
+        let key = Key::from(test_bytes());
+        assert_eq!(
+            format!("{:?}", key),
+            String::from(
+                "Key(0x\
+                    _0001020304050607\
+                    _08090A0B0C0D0E0F\
+                    _1011121314151617\
+                    _18191A1B1C1D1E1F\
+                )"
+            ),
+        );
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn from_works() {
+        let test_bytes = test_bytes();
+        assert_eq!(Key::from(test_bytes).to_bytes(), test_bytes);
+        assert_eq!(Key::from_bytes_be_fallback(test_bytes).to_bytes(), test_bytes);
+        assert_eq!(Key::from(test_bytes).to_bytes_be_fallback(), test_bytes);
+        assert_eq!(Key::from_bytes_be_fallback(test_bytes).to_bytes_be_fallback(), test_bytes);
+    }
+
+    #[test]
+    fn add_one_to_zero() {
+        let bytes = [0x00; 32];
+        let expected = {
+            let mut bytes = [0x00; 32];
+            bytes[0] = 0x01;
+            bytes
+        };
+        let mut key = Key::from(bytes);
+        key.add_assign(1u64);
+        assert_eq!(key.to_bytes(), expected);
+    }
+
+    #[test]
+    fn add_with_ovfl() {
+        let bytes = *b"\
+            \xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\
+            \x00\x00\x00\x00\x00\x00\x00\x00\
+            \x00\x00\x00\x00\x00\x00\x00\x00\
+            \x00\x00\x00\x00\x00\x00\x00\x00\
+        ";
+        let expected = {
+            let mut expected = [0x00; 32];
+            expected[8] = 0x01;
+            expected
+        };
+        let mut key = Key::from(bytes);
+        key.add_assign(1u64);
+        assert_eq!(key.to_bytes(), expected);
+    }
+
+    #[test]
+    fn add_with_ovfl_2() {
+        let bytes = *b"\
+            \xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\
+            \xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\
+            \x00\x00\x00\x00\x00\x00\x00\x00\
+            \x00\x00\x00\x00\x00\x00\x00\x00\
+        ";
+        let expected = {
+            let mut expected = [0x00; 32];
+            expected[16] = 0x01;
+            expected
+        };
+        let mut key = Key::from(bytes);
+        key.add_assign(1u64);
+        assert_eq!(key.to_bytes(), expected);
+    }
+
+    #[test]
+    fn add_with_ovfl_3() {
+        let bytes = *b"\
+            \xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\
+            \xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\
+            \xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\
+            \x00\x00\x00\x00\x00\x00\x00\x00\
+        ";
+        let expected = {
+            let mut expected = [0x00; 32];
+            expected[24] = 0x01;
+            expected
+        };
+        let mut key = Key::from(bytes);
+        key.add_assign(1u64);
+        assert_eq!(key.to_bytes(), expected);
+    }
+
+    #[test]
+    fn add_with_wrap() {
+        let bytes = [0xFF; 32];
+        let expected = [0x00; 32];
+        let mut key = Key::from(bytes);
+        key.add_assign(1u64);
+        assert_eq!(key.to_bytes(), expected);
+    }
+
+    #[test]
+    fn add_assign_to_zero() {
+        for test_value in &[0_u64, 1, 42, 10_000, u32::MAX as u64, u64::MAX] {
+            let mut key = <Key as Default>::default();
+            let expected = {
+                let mut expected = [0x00; 32];
+                expected[0..8].copy_from_slice(&test_value.to_le_bytes());
+                expected
+            };
+            key.add_assign(*test_value);
+            assert_eq!(key.to_bytes(), expected);
+        }
+    }
+
+    #[test]
+    fn shl_works() {
+        let mut bytes = [0x00; 32];
+        bytes[0] = 0x01;
+        let expected = {
+            let mut expected = [0x00; 32];
+            expected[1] = 0x01;
+            expected
+        };
+        assert_eq!((Key::from(bytes) << 8).to_bytes(), expected);
+        // Shifting across limb boundaries.
+        let expected = {
+            let mut expected = [0x00; 32];
+            expected[12] = 0x10;
+            expected
+        };
+        assert_eq!((Key::from(bytes) << 100).to_bytes(), expected);
+        // Bits shifted past the most significant end are dropped.
+        let mut bytes = [0x00; 32];
+        bytes[31] = 0x80;
+        assert_eq!((Key::from(bytes) << 1).to_bytes(), [0x00; 32]);
+        assert_eq!((Key::from([0xFF; 32]) << 256).to_bytes(), [0x00; 32]);
+    }
+
+    #[test]
+    fn shr_works() {
+        let mut bytes = [0x00; 32];
+        bytes[31] = 0x80;
+        let expected = {
+            let mut expected = [0x00; 32];
+            expected[31] = 0x40;
+            expected
+        };
+        assert_eq!((Key::from(bytes) >> 1).to_bytes(), expected);
+        // Shifting across limb boundaries.
+        let expected = {
+            let mut expected = [0x00; 32];
+            expected[19] = 0x08;
+            expected
+        };
+        assert_eq!((Key::from(bytes) >> 100).to_bytes(), expected);
+        // Bits shifted past the least significant end are dropped.
+        let mut bytes = [0x00; 32];
+        bytes[0] = 0x01;
+        assert_eq!((Key::from(bytes) >> 1).to_bytes(), [0x00; 32]);
+        assert_eq!((Key::from([0xFF; 32]) >> 256).to_bytes(), [0x00; 32]);
+    }
+
+    #[test]
+    fn shl_shr_roundtrip() {
+        let key = Key::from(test_bytes());
+        for n in &[0_u32, 1, 7, 8, 63, 64, 65, 128, 200] {
+            let masked = (key << *n) >> *n;
+            assert_eq!(masked << *n, key << *n);
+        }
+    }
+
+    #[test]
+    fn from_u64_le_be_works() {
+        let value = 0x0102_0304_0506_0708_u64;
+        let le = Key::from_u64_le(value);
+        let be = Key::from_u64_be(value);
+        let expected_le = {
+            let mut expected = [0x00; 32];
+            expected[0..8].copy_from_slice(&value.to_le_bytes());
+            expected
+        };
+        let expected_be = {
+            let mut expected = [0x00; 32];
+            expected[24..32].copy_from_slice(&value.to_be_bytes());
+            expected
+        };
+        assert_eq!(le.to_bytes(), expected_le);
+        assert_eq!(be.to_bytes(), expected_be);
+        assert_eq!(le, <Key as Default>::default() + value);
+    }
+
+    #[test]
+    fn to_u64_le_be_works() {
+        for test_value in &[0_u64, 1, 42, u32::MAX as u64, u64::MAX] {
+            assert_eq!(Key::from_u64_le(*test_value).to_u64_le(), Some(*test_value));
+            assert_eq!(Key::from_u64_be(*test_value).to_u64_be(), Some(*test_value));
+        }
+        let le = Key::from_u64_le(42);
+        let be = Key::from_u64_be(42);
+        assert_eq!(le.to_u64_be(), None);
+        assert_eq!(be.to_u64_le(), None);
+        assert_eq!(Key::from(test_bytes()).to_u64_le(), None);
+        assert_eq!(Key::from(test_bytes()).to_u64_be(), None);
+    }
+
+    #[test]
+    fn morton_roundtrip_works() {
+        let test_values = [
+            (0, 0),
+            (1, 0),
+            (0, 1),
+            (42, 1337),
+            (u32::MAX as u64, 5),
+            (u64::MAX, 0),
+            (0, u64::MAX),
+            (u64::MAX, u64::MAX),
+            (0x0123_4567_89AB_CDEF, 0xFEDC_BA98_7654_3210),
+        ];
+        for &(x, y) in &test_values {
+            assert_eq!(Key::morton_encode(x, y).morton_decode(), (x, y));
+        }
+    }
+
+    #[test]
+    fn morton_encode_works() {
+        assert_eq!(Key::morton_encode(0, 0), Key::default());
+        assert_eq!(Key::morton_encode(1, 0), Key::from_u64_le(0b01));
+        assert_eq!(Key::morton_encode(0, 1), Key::from_u64_le(0b10));
+        assert_eq!(Key::morton_encode(3, 3), Key::from_u64_le(0b1111));
+        let expected = {
+            let mut expected = [0xFF; 32];
+            expected[16..32].copy_from_slice(&[0x00; 16]);
+            expected
+        };
+        assert_eq!(Key::morton_encode(u64::MAX, u64::MAX).to_bytes(), expected);
+    }
+
+    #[test]
+    fn morton_adjacent_cells_are_near() {
+        for y in 0..4 {
+            for x in (0..16).step_by(2) {
+                assert_eq!(Key::morton_encode(x + 1, y), Key::morton_encode(x, y) + 1);
+                assert_eq!(Key::morton_encode(y, x + 1), Key::morton_encode(y, x) + 2);
+            }
+        }
+    }
+
+    #[test]
+    fn overflowing_add_works() {
+        let (key, ovfl) = Key::from([0xFF; 32]).overflowing_add(2);
+        assert_eq!(key, Key::from_u64_le(1));
+        assert!(ovfl);
+        let (key, ovfl) = Key::from_u64_le(u64::MAX).overflowing_add(1);
+        let expected = {
+            let mut expected = [0x00; 32];
+            expected[8] = 0x01;
+            expected
+        };
+        assert_eq!(key.to_bytes(), expected);
+        assert!(!ovfl);
+    }
+
+    #[test]
+    fn overflowing_sub_works() {
+        let (key, unfl) = Key::from_u64_le(1).overflowing_sub(2);
+        assert_eq!(key.to_bytes(), [0xFF; 32]);
+        assert!(unfl);
+        let bytes = {
+            let mut bytes = [0x00; 32];
+            bytes[8] = 0x01;
+            bytes
+        };
+        let (key, unfl) = Key::from(bytes).overflowing_sub(1);
+        assert_eq!(key, Key::from_u64_le(u64::MAX));
+        assert!(!unfl);
+    }
+
+    #[test]
+    fn saturating_add_works() {
+        // Exact fit: no clamping.
+        let mut bytes = [0xFF; 32];
+        bytes[0] = 0xFE;
+        assert_eq!(Key::from(bytes).saturating_add(1).to_bytes(), [0xFF; 32]);
+        // Overflow: clamps to all `0xFF`.
+        assert_eq!(Key::from(bytes).saturating_add(2).to_bytes(), [0xFF; 32]);
+        assert_eq!(
+            Key::from([0xFF; 32]).saturating_add(u64::MAX).to_bytes(),
+            [0xFF; 32]
+        );
+        assert_eq!(Key::from_u64_le(1).saturating_add(41), Key::from_u64_le(42));
+    }
+
+    #[test]
+    fn saturating_sub_works() {
+        // Exact fit: no clamping.
+        assert_eq!(
+            Key::from_u64_le(42).saturating_sub(42).to_bytes(),
+            [0x00; 32]
+        );
+        // Underflow: clamps to all `0x00`.
+        assert_eq!(
+            Key::from_u64_le(42).saturating_sub(43).to_bytes(),
+            [0x00; 32]
+        );
+        assert_eq!(Key::from([0xFF; 32]).saturating_sub(u64::MAX).to_bytes(), {
+            let mut expected = [0xFF; 32];
+            expected[0..8].copy_from_slice(&[0x00; 8]);
+            expected
+        });
+    }
+
+    #[test]
+    fn clamp_key_works() {
+        let min = Key::from_u64_le(10);
+        let max = Key::from_u64_le(20);
+        // Below range.
+        assert_eq!(Key::from_u64_le(5).clamp_key(min, max), min);
+        // In range.
+        assert_eq!(Key::from_u64_le(10).clamp_key(min, max), min);
+        assert_eq!(
+            Key::from_u64_le(15).clamp_key(min, max),
+            Key::from_u64_le(15)
+        );
+        assert_eq!(Key::from_u64_le(20).clamp_key(min, max), max);
+        // Above range.
+        assert_eq!(Key::from_u64_le(25).clamp_key(min, max), max);
+        // Single key range.
+        assert_eq!(Key::from_u64_le(42).clamp_key(min, min), min);
+    }
+
+    #[test]
+    fn clamp_key_is_numeric() {
+        let min = Key::from_u64_le(10);
+        let max = Key::from_u64_le(20);
+        // The derived `Ord` sees the key in range since it compares the least
+        // significant limb first, but numerically the key is above the range.
+        let mut bytes = Key::from_u64_le(15).to_bytes();
+        bytes[31] = 0x01;
+        let key = Key::from(bytes);
+        assert!(min < key && key < max);
+        assert!(key.to_int() > max.to_int());
+        assert_eq!(key.clamp_key(min, max), max);
+        // The derived `Ord` sees the key above the range, but numerically the
+        // key is in range.
+        let max = Key::from_u64_be(1);
+        let key = Key::from_u64_le(u64::MAX);
+        assert!(key > max);
+        assert!(key.to_int() < max.to_int());
+        assert_eq!(key.clamp_key(min, max), key);
+    }
+
+    #[test]
+    #[should_panic(expected = "encountered invalid key range: min > max")]
+    fn clamp_key_invalid_range_fails() {
+        Key::from_u64_le(15).clamp_key(Key::from_u64_le(20), Key::from_u64_le(10));
+    }
+
+    #[test]
+    fn zero_and_max_works() {
+        assert_eq!(Key::ZERO, <Key as Default>::default());
+        assert_eq!(Key::ZERO.to_bytes(), [0x00; 32]);
+        assert_eq!(Key::MAX.to_bytes(), [0xFF; 32]);
+    }
+
+    #[test]
+    fn checked_add_sub_works() {
+        assert_eq!(Key::ZERO.checked_add(42), Some(Key::from_u64_le(42)));
+        assert_eq!(Key::MAX.checked_add(1), None);
+        assert_eq!(Key::from_u64_le(42).checked_sub(42), Some(Key::ZERO));
+        assert_eq!(Key::ZERO.checked_sub(1), None);
+    }
+
+    #[test]
+    fn next_prev_works() {
+        assert_eq!(Key::MAX.next(), None);
+        assert_eq!(Key::ZERO.prev(), None);
+        assert_eq!(Key::ZERO.next(), Some(Key::from_u64_le(1)));
+        let mut max_minus_one = [0xFF; 32];
+        max_minus_one[0] = 0xFE;
+        assert_eq!(Key::MAX.prev(), Some(Key::from(max_minus_one)));
+        let key = Key::from(test_bytes());
+        assert_eq!(key.next().and_then(Key::prev), Some(key));
+        assert_eq!(key.prev().and_then(Key::next), Some(key));
+        // Crossing a limb boundary.
+        let expected = {
+            let mut expected = [0x00; 32];
+            expected[8] = 0x01;
+            expected
+        };
+        assert_eq!(
+            Key::from_u64_le(u64::MAX).next().map(|key| key.to_bytes()),
+            Some(expected)
+        );
+    }
+
+    #[test]
+    fn try_from_slice_works() {
+        let test_bytes = test_bytes();
+        assert_eq!(Key::try_from(&test_bytes[..]), Ok(Key::from(test_bytes)));
+        // Too short.
+        assert_eq!(Key::try_from(&test_bytes[..31]), Err(KeyLengthError));
+        assert_eq!(Key::try_from(&[][..]), Err(KeyLengthError));
+        // Too long.
+        assert_eq!(Key::try_from(&[0x00; 33][..]), Err(KeyLengthError));
+    }
+
+    #[test]
+    fn wrapping_add_works() {
+        assert_eq!(Key::MAX.wrapping_add(1u32), Key::ZERO);
+        assert_eq!(Key::MAX.wrapping_add(2u64), Key::from_u64_le(1));
+        let expected = {
+            let mut expected = [0x00; 32];
+            expected[0..16].copy_from_slice(&(u128::MAX - 1).to_le_bytes());
+            expected
+        };
+        assert_eq!(Key::MAX.wrapping_add(u128::MAX).to_bytes(), expected);
+        assert_eq!(Key::ZERO.wrapping_add(42u32), Key::from_u64_le(42));
+        // Carries from the `u128` into the upper limbs.
+        let expected = {
+            let mut expected = [0x00; 32];
+            expected[16] = 0x01;
+            expected
+        };
+        assert_eq!(
+            Key::from_u64_le(1).wrapping_add(u128::MAX).to_bytes(),
+            expected
+        );
+    }
+
+    #[test]
+    fn wrapping_sub_works() {
+        assert_eq!(Key::ZERO.wrapping_sub(1u32), Key::MAX);
+        assert_eq!(Key::from_u64_le(42).wrapping_sub(42u64), Key::ZERO);
+        let bytes = {
+            let mut bytes = [0x00; 32];
+            bytes[16] = 0x01;
+            bytes
+        };
+        assert_eq!(
+            Key::from(bytes).wrapping_sub(u128::MAX),
+            Key::from_u64_le(1)
+        );
+        for value in &[0_u64, 1, 42, u64::MAX] {
+            let key = Key::from(test_bytes());
+            assert_eq!(key.wrapping_add(*value).wrapping_sub(*value), key);
+            assert_eq!(key.wrapping_add(*value), key + *value);
+        }
+    }
+
+    #[test]
+    fn rotate_left_works() {
+        let mut bytes = [0x00; 32];
+        bytes[31] = 0x80;
+        let expected = {
+            let mut expected = [0x00; 32];
+            expected[0] = 0x01;
+            expected
+        };
+        assert_eq!(Key::from(bytes).rotate_left(1).to_bytes(), expected);
+        // The first byte holds the least significant bits.
+        let mut bytes = [0x00; 32];
+        bytes[0] = 0x80;
+        let mut expected = [0x00; 32];
+        expected[1] = 0x01;
+        assert_eq!(Key::from(bytes).rotate_left(1).to_bytes(), expected);
+        let key = Key::from(test_bytes());
+        assert_eq!(key.rotate_left(0), key);
+        assert_eq!(key.rotate_left(256), key);
+        assert_eq!(key.rotate_left(300), key.rotate_left(44));
+        // Rotating by whole bytes moves the bytes around the ring.
+        let mut expected = test_bytes();
+        expected.rotate_right(1);
+        assert_eq!(key.rotate_left(8).to_bytes(), expected);
+    }
+
+    #[test]
+    fn rotate_right_works() {
+        let mut bytes = [0x00; 32];
+        bytes[0] = 0x01;
+        let expected = {
+            let mut expected = [0x00; 32];
+            expected[31] = 0x80;
+            expected
+        };
+        assert_eq!(Key::from(bytes).rotate_right(1).to_bytes(), expected);
+        let key = Key::from(test_bytes());
+        assert_eq!(key.rotate_right(0), key);
+        assert_eq!(key.rotate_right(256), key);
+        for n in &[1_u32, 7, 64, 100, 255] {
+            assert_eq!(key.rotate_left(*n).rotate_right(*n), key);
+        }
+    }
+
+    #[test]
+    fn ct_eq_works() {
+        let key = Key::from(test_bytes());
+        assert!(key.ct_eq(&key));
+        assert!(Key::ZERO.ct_eq(&Key::from([0x00; 32])));
+        assert!(!key.ct_eq(&Key::ZERO));
+        for i in 0..32 {
+            let mut bytes = test_bytes();
+            bytes[i] ^= 0x01;
+            let other = Key::from(bytes);
+            assert_eq!(key.ct_eq(&other), key == other);
+            assert!(!key.ct_eq(&other));
+        }
+    }
+
+    #[test]
+    fn count_ones_zeros_works() {
+        assert_eq!(Key::ZERO.count_ones(), 0);
+        assert_eq!(Key::ZERO.count_zeros(), 256);
+        assert_eq!(Key::MAX.count_ones(), 256);
+        assert_eq!(Key::MAX.count_zeros(), 0);
+        let mut bytes = [0x00; 32];
+        bytes[0] = 0b1011_0000;
+        bytes[17] = 0xFF;
+        bytes[31] = 0x01;
+        assert_eq!(Key::from(bytes).count_ones(), 12);
+        assert_eq!(Key::from(bytes).count_zeros(), 244);
+    }
+
+    #[test]
+    fn leading_zeros_works() {
+        assert_eq!(Key::ZERO.leading_zeros(), 256);
+        assert_eq!(Key::MAX.leading_zeros(), 0);
+        let mut bytes = [0x00; 32];
+        bytes[0] = 0x01;
+        assert_eq!(Key::from(bytes).leading_zeros(), 255);
+        bytes[8] = 0x01;
+        assert_eq!(Key::from(bytes).leading_zeros(), 191);
+        bytes[31] = 0x01;
+        assert_eq!(Key::from(bytes).leading_zeros(), 7);
+        // The last byte holds the most significant bits.
+        let mut bytes = [0x00; 32];
+        bytes[31] = 0x01;
+        assert_eq!(Key::from(bytes).leading_zeros(), 7);
+    }
+
+    #[test]
+    fn mask_prefix_works() {
+        let key = Key::from(test_bytes());
+        assert_eq!(key.mask_prefix(0), Key::ZERO);
+        assert_eq!(Key::MAX.mask_prefix(0), Key::ZERO);
+        assert_eq!(key.mask_prefix(256), key);
+        assert_eq!(key.mask_prefix(300), key);
+        // Masking by whole bytes keeps the most significant bytes.
+        let mut expected = [0x00; 32];
+        expected[24..].copy_from_slice(&test_bytes()[24..]);
+        assert_eq!(key.mask_prefix(64).to_bytes(), expected);
+        let mut expected = [0x00; 32];
+        expected[31] = 0x80;
+        assert_eq!(Key::MAX.mask_prefix(1).to_bytes(), expected);
+        let mut expected = [0xFF; 32];
+        expected[0] = 0xFE;
+        assert_eq!(Key::MAX.mask_prefix(255).to_bytes(), expected);
+        // Keys sharing a prefix are masked to the same key.
+        let mut bytes = test_bytes();
+        bytes[0] ^= 0xFF;
+        assert_eq!(Key::from(bytes).mask_prefix(128), key.mask_prefix(128));
+        assert_ne!(Key::from(bytes).mask_prefix(256), key.mask_prefix(256));
+    }
+
+    #[test]
+    fn gray_code_roundtrip_works() {
+        let keys = [
+            Key::ZERO,
+            Key::MAX,
+            Key::from(test_bytes()),
+            Key::from([0x5A; 32]),
+            Key::from_u64_le(u64::MAX),
+        ];
+        for key in keys.iter() {
+            assert_eq!(key.to_gray().from_gray(), *key);
+            assert_eq!(key.from_gray().to_gray(), *key);
+        }
+    }
+
+    #[test]
+    fn gray_code_adjacency_works() {
+        assert_eq!(Key::ZERO.to_gray(), Key::ZERO);
+        assert_eq!(Key::from_u64_le(2).to_gray(), Key::from_u64_le(3));
+        for n in 0..1000_u64 {
+            let current = Key::from_u64_le(n).to_gray();
+            let next = Key::from_u64_le(n + 1).to_gray();
+            assert_eq!(current.xor(next).count_ones(), 1);
+        }
+        // Also across limb boundaries.
+        let current = Key::from_u64_le(u64::MAX);
+        let next = current + 1;
+        assert_eq!(current.to_gray().xor(next.to_gray()).count_ones(), 1);
+    }
+
+    #[test]
+    fn cmp_from_works() {
+        let mut lhs = [0x00; 32];
+        lhs[0] = 0x01;
+        let mut rhs = [0x00; 32];
+        rhs[31] = 0x01;
+        let (lhs, rhs) = (Key::from(lhs), Key::from(rhs));
+        // Starting at the first byte the difference in byte 0 decides.
+        assert_eq!(lhs.cmp_from(&rhs, 0), Ordering::Greater);
+        // Starting at the last byte the difference in byte 31 decides.
+        assert_eq!(lhs.cmp_from(&rhs, 31), Ordering::Less);
+        // Starting offsets wrap around.
+        assert_eq!(lhs.cmp_from(&rhs, 32), Ordering::Greater);
+        assert_eq!(lhs.cmp_from(&rhs, 63), Ordering::Less);
+        // Equal keys are equal from every origin.
+        let key = Key::from(test_bytes());
+        for start in 0..32 {
+            assert_eq!(key.cmp_from(&key, start), Ordering::Equal);
+            assert_eq!(
+                lhs.cmp_from(&rhs, start),
+                rhs.cmp_from(&lhs, start).reverse()
+            );
+        }
+    }
+
+    #[test]
+    fn next_probe_works() {
+        let key = Key::from(test_bytes());
+        assert_eq!(key.next_probe(0), key);
+        let mut probes = [Key::ZERO; 16];
+        for (i, probe) in probes.iter_mut().enumerate() {
+            *probe = key.next_probe(i as u32);
+            // Probing is deterministic.
+            assert_eq!(*probe, key.next_probe(i as u32));
+        }
+        for (i, lhs) in probes.iter().enumerate() {
+            for rhs in &probes[(i + 1)..] {
+                assert_ne!(lhs, rhs);
+            }
+        }
+        // Different keys yield different probes.
+        assert_ne!(key.next_probe(1), Key::ZERO.next_probe(1));
+        assert_ne!(Key::ZERO.next_probe(1), Key::ZERO);
+    }
+
+    #[test]
+    fn bucket_works() {
+        let key = Key::from(test_bytes());
+        assert_eq!(key.bucket(1), 0);
+        assert_eq!(key.bucket(u64::MAX), key.bucket(u64::MAX));
+        assert_eq!(Key::ZERO.bucket(7), 0);
+        // All limbs of `Key::MAX` cancel each other out.
+        assert_eq!(Key::MAX.bucket(7), 0);
+        let mut counts = [0_u32; 10];
+        for i in 0..1000 {
+            let bucket = Key::from_u64_le(i).bucket(10);
+            assert!(bucket < 10);
+            counts[bucket as usize] += 1;
+        }
+        assert!(counts.iter().all(|&count| count == 100));
+    }
+
+    #[test]
+    #[should_panic(expected = "encountered zero buckets")]
+    fn bucket_zero_fails() {
+        Key::from(test_bytes()).bucket(0);
+    }
+
+    #[test]
+    fn base58_works() {
+        assert_eq!(Key::ZERO.to_base58(), "1".repeat(32));
+        assert_eq!(
+            Key::MAX.to_base58(),
+            "JEKNVnkbo3jma5nREBBJCDoXFVeKkD56V3xKrvRmWxFG"
+        );
+        assert_eq!(
+            Key::from(test_bytes()).to_base58(),
+            "1thX6LZfHDZZKUs92febYZhYRcXddmzfzF2NvTkPNE"
+        );
+        let keys = [
+            Key::ZERO,
+            Key::MAX,
+            Key::from(test_bytes()),
+            Key::from([0x5A; 32]),
+            Key::from_u64_le(1),
+        ];
+        for key in keys.iter() {
+            assert_eq!(Key::from_base58(&key.to_base58()), Ok(*key));
+        }
+    }
+
+    #[test]
+    fn from_base58_invalid_fails() {
+        // `0`, `O`, `I` and `l` are not part of the alphabet.
+        assert_eq!(
+            Key::from_base58("0thX6LZfHDZZKUs92febYZhYRcXddmzfzF2NvTkPNE"),
+            Err(KeyParseError::InvalidCharacter)
+        );
+        assert_eq!(
+            Key::from_base58("1thX6LZfHDZZKUs92febYZhYRcXddmzfzF2NvTkPNl"),
+            Err(KeyParseError::InvalidCharacter)
+        );
+        assert_eq!(Key::from_base58(""), Err(KeyParseError::InvalidLength));
+        assert_eq!(
+            Key::from_base58(&"1".repeat(31)),
+            Err(KeyParseError::InvalidLength)
+        );
+        assert_eq!(
+            Key::from_base58(&"1".repeat(33)),
+            Err(KeyParseError::InvalidLength)
+        );
+        assert_eq!(
+            Key::from_base58("JEKNVnkbo3jma5nREBBJCDoXFVeKkD56V3xKrvRmWxFH"),
+            Err(KeyParseError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn ring_successors_works() {
+        assert_eq!(Key::ZERO.ring_successors(0).count(), 0);
+        // Three successors split the ring into four quarters.
+        let quarters = Key::ZERO
+            .ring_successors(3)
+            .map(|key| key.to_bytes()[31])
+            .collect::<Vec<_>>();
+        assert_eq!(quarters, vec![0x40, 0x80, 0xC0]);
+        // Two successors split the ring into thirds.
+        let thirds = Key::ZERO.ring_successors(2).collect::<Vec<_>>();
+        assert_eq!(thirds, vec![Key::from([0x55; 32]), Key::from([0xAA; 32])]);
+        // The spacing is independent of the start key.
+        let start = Key::from(test_bytes());
+        for (successor, third) in start.ring_successors(2).zip(thirds.iter()) {
+            assert_eq!(successor, (start.to_int() + third.to_int()).to_key());
+        }
+    }
+
+    #[test]
+    fn ring_successors_wrap_around() {
+        // Half of the ring after `Key::MAX` wraps around past `Key::ZERO`.
+        let mut expected = [0xFF; 32];
+        expected[31] = 0x7F;
+        assert_eq!(
+            Key::MAX.ring_successors(1).collect::<Vec<_>>(),
+            vec![Key::from(expected)]
+        );
+        let mut start = [0x00; 32];
+        start[31] = 0xC0;
+        let wrapped = Key::from(start)
+            .ring_successors(3)
+            .map(|key| key.to_bytes()[31])
+            .collect::<Vec<_>>();
+        assert_eq!(wrapped, vec![0x00, 0x40, 0x80]);
+    }
+
+    #[test]
+    fn key_int_roundtrip_works() {
+        let key = Key::from(test_bytes());
+        assert_eq!(key.to_int().to_key(), key);
+        assert_eq!(Key::ZERO.to_int(), KeyInt::default());
+        assert_eq!(Key::from_u64_le(42).to_int(), KeyInt::from(42));
+    }
+
+    #[test]
+    fn key_int_add_works() {
+        assert_eq!(KeyInt::from(2) + KeyInt::from(3), KeyInt::from(5));
+        // Carries propagate through all limbs.
+        let mut bytes = [0xFF; 32];
+        bytes[31] = 0x00;
+        let mut expected = [0x00; 32];
+        expected[31] = 0x01;
+        assert_eq!(
+            (Key::from(bytes).to_int() + KeyInt::from(1)).to_key(),
+            Key::from(expected)
+        );
+        // Additions wrap around at the boundary.
+        assert_eq!(Key::MAX.to_int() + KeyInt::from(1), KeyInt::default());
+        assert_eq!(
+            Key::MAX.to_int().overflowing_add(KeyInt::from(1)),
+            (KeyInt::default(), true)
+        );
+        assert_eq!(
+            KeyInt::from(1).overflowing_add(KeyInt::from(1)),
+            (KeyInt::from(2), false)
+        );
+    }
+
+    #[test]
+    fn key_int_sub_works() {
+        assert_eq!(KeyInt::from(5) - KeyInt::from(3), KeyInt::from(2));
+        // Borrows propagate through all limbs.
+        let mut bytes = [0x00; 32];
+        bytes[31] = 0x01;
+        let mut expected = [0xFF; 32];
+        expected[31] = 0x00;
+        assert_eq!(
+            (Key::from(bytes).to_int() - KeyInt::from(1)).to_key(),
+            Key::from(expected)
+        );
+        // Subtractions wrap around at the boundary.
+        assert_eq!(KeyInt::default() - KeyInt::from(1), Key::MAX.to_int());
+        assert_eq!(
+            KeyInt::default().overflowing_sub(KeyInt::from(1)),
+            (Key::MAX.to_int(), true)
+        );
+        assert_eq!(
+            KeyInt::from(1).overflowing_sub(KeyInt::from(1)),
+            (KeyInt::default(), false)
+        );
+    }
+
+    #[test]
+    fn key_int_mul_div_small_works() {
+        assert_eq!(KeyInt::from(6).mul_small(7), KeyInt::from(42));
+        let large = Key::from_u64_le(u64::MAX).to_int();
+        let product = large.mul_small(u64::MAX);
+        assert_eq!(product.div_rem_small(u64::MAX), (large, 0));
+        assert_eq!(KeyInt::from(43).div_rem_small(7), (KeyInt::from(6), 1));
+        // Multiplications wrap around at the boundary.
+        assert_eq!(
+            Key::MAX.to_int().mul_small(2),
+            Key::MAX.to_int() - KeyInt::from(1)
+        );
+    }
+
+    #[test]
+    fn key_int_ord_is_numeric() {
+        let mut low = [0x00; 32];
+        low[0] = 0xFF;
+        let mut high = [0x00; 32];
+        high[31] = 0x01;
+        let (low, high) = (Key::from(low), Key::from(high));
+        // The ordering of keys is not numeric but the one of integers is.
+        assert!(low > high);
+        assert!(low.to_int() < high.to_int());
+        assert!(KeyInt::from(1) < KeyInt::from(2));
+        assert!(Key::MAX.to_int() > Key::ZERO.to_int());
+    }
+
+    #[test]
+    fn child_works() {
+        let root = Key::from(test_bytes());
+        // Child keys are deterministic.
+        assert_eq!(root.child(0), root.child(0));
+        assert_ne!(root.child(0), root);
+        assert_ne!(root.child(0), root.child(1));
+        assert_ne!(root.child(0), Key::ZERO.child(0));
+    }
+
+    #[test]
+    fn derive_path_works() {
+        let root = Key::from(test_bytes());
+        assert_eq!(root.derive_path(&[]), root);
+        assert_eq!(root.derive_path(&[7]), root.child(7));
+        assert_eq!(
+            root.derive_path(&[1, 2, 3]),
+            root.child(1).child(2).child(3)
+        );
+        assert_eq!(root.derive_path(&[1, 2, 3]), root.derive_path(&[1, 2, 3]));
+        // Different paths yield different keys.
+        assert_ne!(root.derive_path(&[1, 2]), root.derive_path(&[2, 1]));
+        assert_ne!(root.derive_path(&[1, 2]), root.derive_path(&[1, 2, 0]));
+        assert_ne!(root.derive_path(&[0]), root.derive_path(&[0, 0]));
     }
 }
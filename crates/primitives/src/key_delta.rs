@@ -0,0 +1,187 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    Key,
+    KeyInt,
+};
+use core::fmt;
+use ink_prelude::vec::Vec;
+
+/// The maximum number of 7-bit groups required to encode a 256-bit integer.
+const MAX_GROUPS: usize = 37;
+
+/// Compact codec for sets of keys based on the differences between them.
+///
+/// # Note
+///
+/// The keys are sorted by their numeric value and every key is encoded as
+/// its difference to the previous key using a variable-length integer
+/// encoding with 7 bits per byte. Keys that are close to each other, such as
+/// the keys of the cells of storage collections, therefore only require
+/// very few bytes instead of 32 bytes each.
+#[derive(Debug, Copy, Clone)]
+pub struct KeyDelta;
+
+/// Error returned when decoding keys encoded by [`KeyDelta`] failed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyDeltaError {
+    /// The encoding ended in the middle of a key difference.
+    UnexpectedEnd,
+    /// A decoded key is out of the 256-bit range of keys.
+    Overflow,
+}
+
+impl fmt::Display for KeyDeltaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "encountered unexpected end of key deltas"),
+            Self::Overflow => write!(f, "encountered overflowing key delta"),
+        }
+    }
+}
+
+impl KeyDelta {
+    /// Returns the delta encoding of the given keys.
+    ///
+    /// # Note
+    ///
+    /// The order of the keys is not preserved. Decoding yields the keys sorted
+    /// by their numeric value as returned by [`Key::to_int`].
+    pub fn encode_keys(keys: &[Key]) -> Vec<u8> {
+        let mut sorted = keys.iter().map(Key::to_int).collect::<Vec<_>>();
+        sorted.sort_unstable();
+        let mut encoded = Vec::new();
+        let mut prev = KeyInt::default();
+        for key in sorted {
+            encode_int(key - prev, &mut encoded);
+            prev = key;
+        }
+        encoded
+    }
+
+    /// Decodes the keys from their delta encoding.
+    ///
+    /// This is the inverse of [`KeyDelta::encode_keys`].
+    ///
+    /// # Errors
+    ///
+    /// - If the encoding ends in the middle of a key difference.
+    /// - If a decoded key does not fit into 256 bits.
+    pub fn decode_keys(mut encoded: &[u8]) -> Result<Vec<Key>, KeyDeltaError> {
+        let mut keys = Vec::new();
+        let mut prev = KeyInt::default();
+        while !encoded.is_empty() {
+            let (delta, rest) = decode_int(encoded)?;
+            let (key, overflow) = prev.overflowing_add(delta);
+            if overflow {
+                return Err(KeyDeltaError::Overflow)
+            }
+            keys.push(key.to_key());
+            prev = key;
+            encoded = rest;
+        }
+        Ok(keys)
+    }
+}
+
+/// Appends the variable-length encoding of `value` to `output`.
+fn encode_int(mut value: KeyInt, output: &mut Vec<u8>) {
+    loop {
+        let (rest, group) = value.div_rem_small(0x80);
+        if rest == KeyInt::default() {
+            output.push(group as u8);
+            return
+        }
+        output.push(group as u8 | 0x80);
+        value = rest;
+    }
+}
+
+/// Decodes a variable-length encoded integer from the start of `input`.
+///
+/// Returns the integer and the remaining input.
+fn decode_int(input: &[u8]) -> Result<(KeyInt, &[u8]), KeyDeltaError> {
+    let len = input
+        .iter()
+        .position(|byte| byte & 0x80 == 0)
+        .ok_or(KeyDeltaError::UnexpectedEnd)?
+        + 1;
+    let (groups, rest) = input.split_at(len);
+    // The most significant group may only hold the upper 4 bits.
+    if len > MAX_GROUPS || (len == MAX_GROUPS && groups[len - 1] >= 0x10) {
+        return Err(KeyDeltaError::Overflow)
+    }
+    let value = groups.iter().rev().fold(KeyInt::default(), |acc, group| {
+        acc.mul_small(0x80) + KeyInt::from((group & 0x7F) as u64)
+    });
+    Ok((value, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip_works() {
+        let keys = [
+            Key::from([0x42; 32]),
+            Key::from_u64_le(5),
+            Key::MAX,
+            Key::ZERO,
+            Key::from_u64_le(5),
+            Key::from([0x01; 32]),
+        ];
+        let mut expected = keys.to_vec();
+        expected.sort_unstable_by_key(Key::to_int);
+        let encoded = KeyDelta::encode_keys(&keys);
+        assert_eq!(KeyDelta::decode_keys(&encoded), Ok(expected));
+        assert_eq!(KeyDelta::encode_keys(&[]), Vec::<u8>::new());
+        assert_eq!(KeyDelta::decode_keys(&[]), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn clustered_keys_are_compact() {
+        let offset = Key::from([0x42; 32]);
+        let keys = (0..100).map(|i| offset + i).collect::<Vec<_>>();
+        let encoded = KeyDelta::encode_keys(&keys);
+        assert_eq!(KeyDelta::decode_keys(&encoded), Ok(keys.clone()));
+        // The offset requires all 37 groups and every further key just one.
+        assert_eq!(encoded.len(), 37 + 99);
+        assert!(encoded.len() < keys.len() * 32);
+    }
+
+    #[test]
+    fn decode_invalid_fails() {
+        // The last byte indicates that more bytes follow.
+        assert_eq!(
+            KeyDelta::decode_keys(&[0x01, 0x80]),
+            Err(KeyDeltaError::UnexpectedEnd)
+        );
+        // A single difference that does not fit into 256 bits.
+        let mut too_large = [0xFF; 37];
+        too_large[36] = 0x10;
+        assert_eq!(
+            KeyDelta::decode_keys(&too_large),
+            Err(KeyDeltaError::Overflow)
+        );
+        // The sum of the differences does not fit into 256 bits.
+        let mut encoded = KeyDelta::encode_keys(&[Key::MAX]);
+        encoded.push(0x01);
+        assert_eq!(
+            KeyDelta::decode_keys(&encoded),
+            Err(KeyDeltaError::Overflow)
+        );
+    }
+}
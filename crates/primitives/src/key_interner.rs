@@ -0,0 +1,145 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::Key;
+use ink_prelude::collections::{
+    BTreeMap,
+    VecDeque,
+};
+
+/// A bounded cache of derived keys indexed by the seeds they were derived from.
+///
+/// # Note
+///
+/// Useful in hot loops that derive the same keys over and over again, e.g.
+/// through hashing, in order to trade memory for fewer key derivations.
+/// If the interner is full the least recently derived key is evicted.
+#[derive(Debug, Clone)]
+pub struct KeyInterner<S> {
+    /// The maximum number of interned keys.
+    capacity: usize,
+    /// The interned keys indexed by their seeds.
+    keys: BTreeMap<S, Key>,
+    /// The seeds of the interned keys in the order of their derivation.
+    seeds: VecDeque<S>,
+}
+
+impl<S> KeyInterner<S>
+where
+    S: Ord + Clone,
+{
+    /// Creates a new key interner that holds at most `capacity` keys.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            keys: BTreeMap::new(),
+            seeds: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the maximum number of interned keys.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of currently interned keys.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns `true` if no keys are interned.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns the interned key for the seed or derives it using `derive`.
+    ///
+    /// # Note
+    ///
+    /// A freshly derived key is only interned if the capacity is not zero.
+    pub fn get_or_derive<F>(&mut self, seed: &S, derive: F) -> Key
+    where
+        F: FnOnce(&S) -> Key,
+    {
+        if let Some(key) = self.keys.get(seed) {
+            return *key
+        }
+        let key = derive(seed);
+        if self.capacity == 0 {
+            return key
+        }
+        if self.keys.len() == self.capacity {
+            let evicted = self
+                .seeds
+                .pop_front()
+                .expect("a full interner must have at least one seed");
+            self.keys.remove(&evicted);
+        }
+        self.keys.insert(seed.clone(), key);
+        self.seeds.push_back(seed.clone());
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_or_derive_works() {
+        let mut interner = <KeyInterner<u32>>::new(4);
+        let mut derivations = 0;
+        let mut derive = |seed: &u32| {
+            derivations += 1;
+            Key::from([*seed as u8; 32])
+        };
+        let key = interner.get_or_derive(&1, &mut derive);
+        assert_eq!(key, Key::from([0x01; 32]));
+        // The same seed yields the same key without another derivation.
+        assert_eq!(interner.get_or_derive(&1, &mut derive), key);
+        assert_eq!(
+            interner.get_or_derive(&2, &mut derive),
+            Key::from([0x02; 32])
+        );
+        assert_eq!(derivations, 2);
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn capacity_is_respected() {
+        let mut interner = <KeyInterner<u32>>::new(2);
+        let derive = |seed: &u32| Key::from([*seed as u8; 32]);
+        assert!(interner.is_empty());
+        for seed in 0..10 {
+            interner.get_or_derive(&seed, derive);
+            assert!(interner.len() <= interner.capacity());
+        }
+        assert_eq!(interner.len(), 2);
+        // Only the most recently derived keys remain interned.
+        let not_derived = |_: &u32| -> Key { panic!("unexpected key derivation") };
+        assert_eq!(interner.get_or_derive(&9, not_derived), derive(&9));
+        assert_eq!(interner.get_or_derive(&8, not_derived), derive(&8));
+        // Evicted keys are derived again.
+        assert_eq!(interner.get_or_derive(&0, derive), derive(&0));
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn zero_capacity_works() {
+        let mut interner = <KeyInterner<u32>>::new(0);
+        let key = interner.get_or_derive(&1, |_| Key::from([0x42; 32]));
+        assert_eq!(key, Key::from([0x42; 32]));
+        assert!(interner.is_empty());
+    }
+}
@@ -23,10 +23,23 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+pub mod byte_utils;
 mod key;
+mod key_delta;
+mod key_interner;
 mod key_ptr;
 
 pub use self::{
-    key::Key,
+    key::{
+        Key,
+        KeyInt,
+        KeyLengthError,
+        KeyParseError,
+    },
+    key_delta::{
+        KeyDelta,
+        KeyDeltaError,
+    },
+    key_interner::KeyInterner,
     key_ptr::KeyPtr,
 };
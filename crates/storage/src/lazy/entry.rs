@@ -271,6 +271,11 @@ impl<T> StorageEntry<T> {
         self.state.replace(new_state)
     }
 
+    /// Returns the current state of the entry.
+    pub fn state(&self) -> EntryState {
+        self.state.get()
+    }
+
     /// Returns a shared reference to the value of the entry.
     pub fn value(&self) -> &Option<T> {
         &self.value
@@ -21,14 +21,19 @@ use super::{
 };
 use crate::traits::{
     clear_packed_root,
+    pull_packed_root,
     pull_packed_root_opt,
+    push_packed_root,
     ExtKeyPtr,
     KeyPtr,
     PackedLayout,
     SpreadLayout,
 };
+#[cfg(feature = "debug-collisions")]
+use core::cell::RefCell;
 use core::{
     borrow::Borrow,
+    cell::Cell,
     cmp::{
         Eq,
         Ord,
@@ -40,6 +45,7 @@ use core::{
     ptr::NonNull,
 };
 use ink_env::hash::{
+    Blake2x256,
     CryptoHash,
     HashOutput,
 };
@@ -51,6 +57,7 @@ use ink_prelude::{
         Entry as BTreeMapEntry,
         OccupiedEntry as BTreeMapOccupiedEntry,
     },
+    vec::Vec,
 };
 use ink_primitives::Key;
 
@@ -74,7 +81,10 @@ pub type EntryMap<K, V> = BTreeMap<K, Box<StorageEntry<V>>>;
 /// This storage data structure might store its entires anywhere in the contract
 /// storage. It is the users responsibility to keep track of the entries if it
 /// is necessary to do so.
-pub struct LazyHashMap<K, V, H> {
+///
+/// The salt strategy `S` is either [`Unsalted`] or [`Salted`]. Only salted lazy
+/// hash maps store anything besides their entries in the contract storage.
+pub struct LazyHashMap<K, V, H = Blake2x256, S = Unsalted> {
     /// The offset key for the storage mapping.
     ///
     /// This offsets the mapping for the entries stored in the contract storage
@@ -88,6 +98,151 @@ pub struct LazyHashMap<K, V, H> {
     cached_entries: CacheCell<EntryMap<K, V>>,
     /// The used hash builder.
     hash_builder: PhantomData<H>,
+    /// The optional maximum number of cached entries enforced by
+    /// [`LazyHashMap::try_put`].
+    max_entries: Option<usize>,
+    /// The salt that is mixed into the derivation of offset keys if any.
+    salt: S,
+    /// The number of cache hits and misses upon lazily loading entries.
+    #[cfg(feature = "lazy-stats")]
+    cache_stats: CacheStats,
+    /// The encoded source keys of all derived offset keys.
+    ///
+    /// Used to detect hash collisions between distinct keys.
+    #[cfg(feature = "debug-collisions")]
+    derived_keys: RefCell<BTreeMap<Key, Vec<u8>>>,
+}
+
+mod private {
+    /// Seals the implementation of `KeySalt`.
+    pub trait Sealed {}
+}
+
+/// The salt strategy of a lazy hash map.
+///
+/// # Note
+///
+/// This trait is sealed and implemented by [`Unsalted`] and [`Salted`] only.
+pub trait KeySalt: private::Sealed {
+    /// Returns the salt that is mixed into the offset keys if any.
+    fn salt(&self) -> Option<&[u8; 16]>;
+
+    /// Pulls the salt of the lazy hash map at the given offset key.
+    fn pull_salt(offset_key: &Key) -> Self;
+
+    /// Pushes the salt to the given offset key unless it is already stored there.
+    fn push_salt(&self, offset_key: &Key);
+
+    /// Clears the salt at the given offset key.
+    fn clear_salt(&self, offset_key: &Key);
+}
+
+/// The salt strategy of lazy hash maps that do not salt their offset keys.
+///
+/// This is the default and does not interact with the contract storage.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Unsalted;
+
+impl private::Sealed for Unsalted {}
+
+impl KeySalt for Unsalted {
+    #[inline]
+    fn salt(&self) -> Option<&[u8; 16]> {
+        None
+    }
+
+    #[inline]
+    fn pull_salt(_offset_key: &Key) -> Self {
+        Self
+    }
+
+    #[inline]
+    fn push_salt(&self, _offset_key: &Key) {}
+
+    #[inline]
+    fn clear_salt(&self, _offset_key: &Key) {}
+}
+
+/// The salt strategy of lazy hash maps that mix a salt into their offset keys.
+///
+/// Makes the storage locations of the entries unpredictable for anyone who
+/// does not know the salt. The salt is stored in the otherwise unused storage
+/// cell at the offset key of the lazy hash map.
+#[derive(Debug, Clone)]
+pub struct Salted {
+    /// The salt that is mixed into the offset keys.
+    salt: [u8; 16],
+    /// The offset key at which the salt is already stored if any.
+    ///
+    /// Used to write the salt only once instead of upon every push.
+    stored_at: Cell<Option<Key>>,
+}
+
+impl Salted {
+    /// Creates a new salt that has not been stored, yet.
+    fn new(salt: [u8; 16]) -> Self {
+        Self {
+            salt,
+            stored_at: Cell::new(None),
+        }
+    }
+}
+
+impl private::Sealed for Salted {}
+
+impl KeySalt for Salted {
+    #[inline]
+    fn salt(&self) -> Option<&[u8; 16]> {
+        Some(&self.salt)
+    }
+
+    fn pull_salt(offset_key: &Key) -> Self {
+        Self {
+            salt: pull_packed_root::<[u8; 16]>(offset_key),
+            stored_at: Cell::new(Some(*offset_key)),
+        }
+    }
+
+    fn push_salt(&self, offset_key: &Key) {
+        if self.stored_at.get() != Some(*offset_key) {
+            push_packed_root(&self.salt, offset_key);
+            self.stored_at.set(Some(*offset_key));
+        }
+    }
+
+    fn clear_salt(&self, offset_key: &Key) {
+        clear_packed_root(&self.salt, offset_key);
+        if self.stored_at.get() == Some(*offset_key) {
+            self.stored_at.set(None);
+        }
+    }
+}
+
+/// Inserting another entry would exceed the maximum number of cached entries.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MaxEntriesExceeded;
+
+/// The state of a key in the cache of a lazy hash map.
+///
+/// Returned by [`LazyHashMap::classify`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CacheClass {
+    /// The key has not been cached, yet, so its value is unknown.
+    UncachedUnknown,
+    /// The key is cached and has an associated value.
+    CachedPresent,
+    /// The key is cached but has no associated value.
+    CachedAbsent,
+}
+
+/// Counts the cache hits and misses of a lazy hash map.
+#[cfg(feature = "lazy-stats")]
+#[derive(Default)]
+struct CacheStats {
+    /// The number of lazy loads that found the entry in the cache.
+    hits: Cell<u64>,
+    /// The number of lazy loads that had to query the contract storage.
+    misses: Cell<u64>,
 }
 
 /// When querying `entry()` there is a case which needs special treatment:
@@ -164,11 +319,24 @@ where
     V: Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_map().entries(self.0.as_inner().iter()).finish()
+        if f.alternate() {
+            // The alternate form only shows live entries and skips the
+            // cached `None` entries of removed or non-existing values.
+            f.debug_map()
+                .entries(
+                    self.0
+                        .as_inner()
+                        .iter()
+                        .filter(|(_, entry)| entry.value().is_some()),
+                )
+                .finish()
+        } else {
+            f.debug_map().entries(self.0.as_inner().iter()).finish()
+        }
     }
 }
 
-impl<K, V, H> Debug for LazyHashMap<K, V, H>
+impl<K, V, H, S> Debug for LazyHashMap<K, V, H, S>
 where
     K: Debug,
     V: Debug,
@@ -217,6 +385,21 @@ fn debug_impl_works() {
     );
 }
 
+#[test]
+fn debug_impl_alternate_skips_none_entries() {
+    use ink_env::hash::Blake2x256;
+    let mut hmap = <LazyHashMap<char, i32, Blake2x256>>::new();
+    hmap.put('A', Some(1));
+    hmap.put('B', Some(2));
+    hmap.put('C', None);
+    let default = format!("{:?}", &hmap);
+    let alternate = format!("{:#?}", &hmap);
+    assert!(default.contains("'C'"));
+    assert!(!alternate.contains("'C'"));
+    assert!(alternate.contains("'A'"));
+    assert!(alternate.contains("'B'"));
+}
+
 #[cfg(feature = "std")]
 const _: () = {
     use crate::traits::{
@@ -225,10 +408,12 @@ const _: () = {
     };
     use ink_metadata::layout::{
         CellLayout,
+        FieldLayout,
         HashLayout,
         HashingStrategy,
         Layout,
         LayoutKey,
+        StructLayout,
     };
     use scale_info::TypeInfo;
 
@@ -253,27 +438,46 @@ const _: () = {
             ))
         }
     }
+
+    impl<K, V, H> StorageLayout for LazyHashMap<K, V, H, Salted>
+    where
+        K: Ord + scale::Encode,
+        V: TypeInfo + 'static,
+        H: CryptoHash + LayoutCryptoHasher,
+        Key: From<<H as HashOutput>::Type>,
+    {
+        fn layout(key_ptr: &mut KeyPtr) -> Layout {
+            // The salt is stored in the cell at the offset key of the entries.
+            let salt =
+                CellLayout::new::<[u8; 16]>(LayoutKey::from(key_ptr.advance_by(0)));
+            Layout::Struct(StructLayout::new(vec![
+                FieldLayout::new("salt", salt),
+                FieldLayout::new(
+                    "entries",
+                    <LazyHashMap<K, V, H> as StorageLayout>::layout(key_ptr),
+                ),
+            ]))
+        }
+    }
 };
 
-impl<K, V, H> SpreadLayout for LazyHashMap<K, V, H>
+impl<K, V, H, S> SpreadLayout for LazyHashMap<K, V, H, S>
 where
     K: Ord + scale::Encode,
     V: PackedLayout,
     H: CryptoHash,
     Key: From<<H as HashOutput>::Type>,
+    S: KeySalt,
 {
     const FOOTPRINT: u64 = 1;
 
     fn pull_spread(ptr: &mut KeyPtr) -> Self {
-        Self::lazy(*ExtKeyPtr::next_for::<Self>(ptr))
+        let key = *ExtKeyPtr::next_for::<Self>(ptr);
+        Self::from_parts(Some(key), S::pull_salt(&key))
     }
 
     fn push_spread(&self, ptr: &mut KeyPtr) {
-        let offset_key = ExtKeyPtr::next_for::<Self>(ptr);
-        for (index, entry) in self.entries().iter() {
-            let root_key = self.to_offset_key(&offset_key, index);
-            entry.push_packed_root(&root_key);
-        }
+        self.push_spread_with(ptr, |_| ())
     }
 
     #[inline]
@@ -320,12 +524,13 @@ where
     }
 }
 
-impl<K, V, H> Extend<(K, V)> for LazyHashMap<K, V, H>
+impl<K, V, H, S> Extend<(K, V)> for LazyHashMap<K, V, H, S>
 where
     K: Ord + Clone + PackedLayout,
     V: PackedLayout,
     H: CryptoHash,
     Key: From<<H as HashOutput>::Type>,
+    S: KeySalt,
 {
     fn extend<I>(&mut self, iter: I)
     where
@@ -348,11 +553,7 @@ where
     /// A lazy map created this way cannot be used to load from the contract storage.
     /// All operations that directly or indirectly load from storage will panic.
     pub fn new() -> Self {
-        Self {
-            key: None,
-            cached_entries: CacheCell::new(EntryMap::new()),
-            hash_builder: Default::default(),
-        }
+        Self::from_parts(None, Unsalted)
     }
 
     /// Creates a new empty lazy hash map positioned at the given key.
@@ -364,10 +565,65 @@ where
     /// key that is only useful upon a contract call. Use [`LazyIndexMap::new`]
     /// for construction during contract initialization.
     fn lazy(key: Key) -> Self {
+        Self::from_parts(Some(key), Unsalted)
+    }
+
+    /// Creates a new empty lazy hash map that caches at most `max_entries` entries.
+    ///
+    /// # Note
+    ///
+    /// The limit is only enforced by [`LazyHashMap::try_put`] and protects
+    /// off-chain environments from unbounded memory growth. All other methods
+    /// that insert into the cache, such as [`LazyHashMap::put`],
+    /// [`LazyHashMap::put_get`], [`LazyHashMap::entry`] or lookups of keys that
+    /// are not yet cached, ignore it.
+    /// Like [`LazyHashMap::new`] the created lazy map cannot be used to load
+    /// from the contract storage.
+    pub fn with_max_entries(max_entries: usize) -> Self {
+        Self {
+            max_entries: Some(max_entries),
+            ..Self::new()
+        }
+    }
+}
+
+impl<K, V, H> LazyHashMap<K, V, H, Salted>
+where
+    K: Ord,
+{
+    /// Creates a new empty lazy hash map that mixes `salt` into the offset keys
+    /// of its entries.
+    ///
+    /// # Note
+    ///
+    /// This mitigates attacks that choose keys in order to provoke storage
+    /// collisions. The salt can for example be derived from randomness upon
+    /// contract instantiation.
+    /// The salt is written to the contract storage upon the first push and
+    /// restored when the lazy hash map is pulled again.
+    /// Like [`LazyHashMap::new`] the created lazy map cannot be used to load
+    /// from the contract storage.
+    pub fn with_salt(salt: [u8; 16]) -> Self {
+        Self::from_parts(None, Salted::new(salt))
+    }
+}
+
+impl<K, V, H, S> LazyHashMap<K, V, H, S>
+where
+    K: Ord,
+{
+    /// Creates a new empty lazy hash map with the given offset key and salt.
+    fn from_parts(key: Option<Key>, salt: S) -> Self {
         Self {
-            key: Some(key),
+            key,
             cached_entries: CacheCell::new(EntryMap::new()),
             hash_builder: Default::default(),
+            max_entries: None,
+            salt,
+            #[cfg(feature = "lazy-stats")]
+            cache_stats: Default::default(),
+            #[cfg(feature = "debug-collisions")]
+            derived_keys: Default::default(),
         }
     }
 
@@ -376,6 +632,49 @@ where
         self.key.as_ref()
     }
 
+    /// Returns the number of cache hits and misses as `(hits, misses)`.
+    ///
+    /// A miss is counted whenever an entry had to be loaded from the contract
+    /// storage and a hit whenever it was already cached. Lookups of uncached
+    /// entries in a map without a key count as neither.
+    #[cfg(feature = "lazy-stats")]
+    pub fn cache_stats(&self) -> (u64, u64) {
+        (self.cache_stats.hits.get(), self.cache_stats.misses.get())
+    }
+
+    /// Records a cache hit or miss for the cache statistics.
+    #[cfg(feature = "lazy-stats")]
+    fn record_cache_access(&self, hit: bool) {
+        let counter = if hit {
+            &self.cache_stats.hits
+        } else {
+            &self.cache_stats.misses
+        };
+        counter.set(counter.get() + 1);
+    }
+
+    /// Remembers the encoded source key of the derived offset key.
+    ///
+    /// # Panics
+    ///
+    /// In debug mode if the same offset key has already been derived from
+    /// a different source key.
+    #[cfg(feature = "debug-collisions")]
+    fn track_offset_key(&self, offset_key: &Key, encoded_key: Vec<u8>) {
+        match self.derived_keys.borrow_mut().entry(*offset_key) {
+            BTreeMapEntry::Occupied(occupied) => {
+                debug_assert!(
+                    occupied.get() == &encoded_key,
+                    "encountered offset key collision for distinct keys at {:?}",
+                    offset_key,
+                );
+            }
+            BTreeMapEntry::Vacant(vacant) => {
+                vacant.insert(encoded_key);
+            }
+        }
+    }
+
     /// Returns the length of the cached entries.
     #[cfg(test)]
     pub(crate) fn len_cached_entries(&self) -> usize {
@@ -411,14 +710,234 @@ where
             Box::new(StorageEntry::new(new_value, EntryState::Mutated)),
         );
     }
+
+    /// Applies all changes of the given changeset in order.
+    ///
+    /// Each change is applied as if by [`LazyHashMap::put`] so that a change
+    /// with a `None` value removes the element under its key.
+    ///
+    /// # Note
+    ///
+    /// This is useful to replay previously recorded mutations.
+    pub fn apply<I>(&mut self, changes: I)
+    where
+        I: IntoIterator<Item = (K, Option<V>)>,
+    {
+        for (key, new_value) in changes {
+            self.put(key, new_value);
+        }
+    }
+
+    /// Applies `f` to all cached entries that hold a value.
+    ///
+    /// # Note
+    ///
+    /// All updated entries are marked as mutated. This only operates on the
+    /// cache and never loads from the contract storage.
+    pub fn update_all<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V),
+    {
+        for (key, entry) in self.entries_mut().iter_mut() {
+            if let Some(value) = entry.value_mut() {
+                f(key, value);
+            }
+        }
+    }
+
+    /// Puts the new value under the given key unless this exceeds the maximum
+    /// number of cached entries.
+    ///
+    /// # Note
+    ///
+    /// Overwriting an already cached key never exceeds the maximum.
+    /// Unlike [`LazyHashMap::put`] this respects the limit set by
+    /// [`LazyHashMap::with_max_entries`].
+    ///
+    /// # Errors
+    ///
+    /// If the key is not yet cached and the cache already holds the maximum
+    /// number of entries.
+    pub fn try_put(
+        &mut self,
+        key: K,
+        new_value: Option<V>,
+    ) -> Result<(), MaxEntriesExceeded> {
+        if let Some(max_entries) = self.max_entries {
+            if self.entries().len() >= max_entries && !self.entries().contains_key(&key) {
+                return Err(MaxEntriesExceeded)
+            }
+        }
+        self.put(key, new_value);
+        Ok(())
+    }
+
+    /// Returns a shared reference to the cached value associated with the given key if any.
+    ///
+    /// # Note
+    ///
+    /// Unlike [`LazyHashMap::get`] this never loads from the contract storage and
+    /// never inserts into the cache. Returns `None` if the key has not been cached, yet.
+    /// Since no key has to be cached the query key does not need to be owned.
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.entries()
+            .get(key)
+            .and_then(|entry| entry.value().into())
+    }
+
+    /// Returns `true` if a value for the given key is present in the cache.
+    ///
+    /// # Note
+    ///
+    /// This never loads from the contract storage and thus returns `false` for keys
+    /// that have not been cached, yet, even if the contract storage holds a value.
+    pub fn contains_only_cached<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.peek(key).is_some()
+    }
+
+    /// Returns whether the given key is cached and whether it has a value.
+    ///
+    /// # Note
+    ///
+    /// This never loads from the contract storage and is useful to decide
+    /// whether it is worth to pay for loading the entry.
+    pub fn classify<Q>(&self, key: &Q) -> CacheClass
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self.entries().get(key) {
+            Some(entry) if entry.value().is_some() => CacheClass::CachedPresent,
+            Some(_) => CacheClass::CachedAbsent,
+            None => CacheClass::UncachedUnknown,
+        }
+    }
+
+    /// Returns the number of cached entries with and without a value as
+    /// `(live, tombstones)`.
+    ///
+    /// # Note
+    ///
+    /// Tombstones are cached entries without a value, for example removed
+    /// entries or entries that have been looked up but do not exist.
+    /// This only inspects the cache and never loads from the contract storage.
+    pub fn cache_breakdown(&self) -> (usize, usize) {
+        let live = self
+            .entries()
+            .values()
+            .filter(|entry| entry.value().is_some())
+            .count();
+        (live, self.entries().len() - live)
+    }
+
+    /// Returns the cached key-value pair with the smallest key that holds a value.
+    ///
+    /// # Note
+    ///
+    /// This skips cached entries without a value and never loads from the
+    /// contract storage.
+    pub fn first_cached(&self) -> Option<(&K, &V)> {
+        self.entries()
+            .iter()
+            .find_map(|(key, entry)| entry.value().as_ref().map(|value| (key, value)))
+    }
+
+    /// Returns the cached key-value pair with the largest key that holds a value.
+    ///
+    /// # Note
+    ///
+    /// This skips cached entries without a value and never loads from the
+    /// contract storage.
+    pub fn last_cached(&self) -> Option<(&K, &V)> {
+        self.entries()
+            .iter()
+            .rev()
+            .find_map(|(key, entry)| entry.value().as_ref().map(|value| (key, value)))
+    }
+
+    /// Returns an owned snapshot of all cached key-value pairs.
+    ///
+    /// # Note
+    ///
+    /// This only contains the cached entries that hold a value and never loads
+    /// from the contract storage. Entries that have not been loaded, yet, are
+    /// not part of the snapshot.
+    pub fn to_btreemap(&self) -> BTreeMap<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.entries()
+            .iter()
+            .filter_map(|(key, entry)| {
+                entry
+                    .value()
+                    .as_ref()
+                    .map(|value| (key.clone(), value.clone()))
+            })
+            .collect()
+    }
+
+    /// Returns the SCALE encoding of all cached entries including their states.
+    ///
+    /// # Note
+    ///
+    /// Useful to snapshot the in-memory state of a lazy hash map in off-chain
+    /// environments. This neither loads from nor writes to the contract storage.
+    pub fn export_cache(&self) -> Vec<u8>
+    where
+        K: scale::Encode,
+        V: scale::Encode,
+    {
+        let entries = self
+            .entries()
+            .iter()
+            .map(|(key, entry)| (key, &**entry, entry.state().is_mutated()))
+            .collect::<Vec<_>>();
+        scale::Encode::encode(&entries)
+    }
+
+    /// Replaces all cached entries with the ones encoded by
+    /// [`LazyHashMap::export_cache`].
+    ///
+    /// # Errors
+    ///
+    /// If `encoded` is not a valid encoding of cached entries.
+    /// The cached entries remain unchanged in this case.
+    pub fn import_cache(&mut self, encoded: &[u8]) -> Result<(), scale::Error>
+    where
+        K: scale::Decode,
+        V: scale::Decode,
+    {
+        let entries =
+            <Vec<(K, StorageEntry<V>, bool)> as scale::DecodeAll>::decode_all(encoded)?;
+        let cached_entries = self.entries_mut();
+        cached_entries.clear();
+        for (key, entry, is_mutated) in entries {
+            if is_mutated {
+                entry.replace_state(EntryState::Mutated);
+            }
+            cached_entries.insert(key, Box::new(entry));
+        }
+        Ok(())
+    }
 }
 
-impl<K, V, H> LazyHashMap<K, V, H>
+impl<K, V, H, S> LazyHashMap<K, V, H, S>
 where
     K: Clone + Ord + PackedLayout,
     V: PackedLayout,
     H: CryptoHash,
     Key: From<<H as HashOutput>::Type>,
+    S: KeySalt,
 {
     /// Gets the given key's corresponding entry in the map for in-place manipulation.
     pub fn entry(&mut self, key: K) -> Entry<K, V> {
@@ -438,6 +957,8 @@ where
         // Read more about the issue here: https://github.com/rust-lang/rust/issues/56167
         match cached_entries.entry(key.to_owned()) {
             BTreeMapEntry::Occupied(entry) => {
+                #[cfg(feature = "lazy-stats")]
+                self.record_cache_access(true);
                 match entry.get().value() {
                     Some(_) => {
                         Entry::Occupied(OccupiedEntry {
@@ -457,7 +978,11 @@ where
             BTreeMapEntry::Vacant(entry) => {
                 let value = self
                     .key_at(&key)
-                    .map(|key| pull_packed_root_opt::<V>(&key))
+                    .map(|key| {
+                        #[cfg(feature = "lazy-stats")]
+                        self.record_cache_access(false);
+                        pull_packed_root_opt::<V>(&key)
+                    })
                     .unwrap_or(None);
                 match value.is_some() {
                     true => {
@@ -487,11 +1012,12 @@ where
     }
 }
 
-impl<K, V, H> LazyHashMap<K, V, H>
+impl<K, V, H, S> LazyHashMap<K, V, H, S>
 where
     K: Ord + scale::Encode,
     H: CryptoHash,
     Key: From<<H as HashOutput>::Type>,
+    S: KeySalt,
 {
     /// Returns an offset key for the given key pair.
     fn to_offset_key<Q>(&self, storage_key: &Key, key: &Q) -> Key
@@ -513,8 +1039,14 @@ where
             value_key: key,
         };
         let mut output = <H as HashOutput>::Type::default();
-        ink_env::hash_encoded::<H, KeyPair<Q>>(&key_pair, &mut output);
-        output.into()
+        match self.salt.salt() {
+            Some(salt) => ink_env::hash_encoded::<H, _>(&(salt, &key_pair), &mut output),
+            None => ink_env::hash_encoded::<H, KeyPair<Q>>(&key_pair, &mut output),
+        }
+        let offset_key = output.into();
+        #[cfg(feature = "debug-collisions")]
+        self.track_offset_key(&offset_key, scale::Encode::encode(key));
+        offset_key
     }
 
     /// Returns an offset key for the given key.
@@ -528,12 +1060,13 @@ where
     }
 }
 
-impl<K, V, H> LazyHashMap<K, V, H>
+impl<K, V, H, S> LazyHashMap<K, V, H, S>
 where
     K: Ord + Eq + scale::Encode,
     V: PackedLayout,
     H: CryptoHash,
     Key: From<<H as HashOutput>::Type>,
+    S: KeySalt,
 {
     /// Lazily loads the value at the given index.
     ///
@@ -575,12 +1108,18 @@ where
         // Read more about the issue here: https://github.com/rust-lang/rust/issues/56167
         match cached_entries.entry(key.to_owned()) {
             BTreeMapEntry::Occupied(occupied) => {
+                #[cfg(feature = "lazy-stats")]
+                self.record_cache_access(true);
                 NonNull::from(&mut **occupied.into_mut())
             }
             BTreeMapEntry::Vacant(vacant) => {
                 let value = self
                     .key_at(key)
-                    .map(|key| pull_packed_root_opt::<V>(&key))
+                    .map(|key| {
+                        #[cfg(feature = "lazy-stats")]
+                        self.record_cache_access(false);
+                        pull_packed_root_opt::<V>(&key)
+                    })
                     .unwrap_or(None);
                 NonNull::from(
                     &mut **vacant.insert(Box::new(StorageEntry::new(
@@ -615,39 +1154,121 @@ where
         unsafe { &mut *self.lazily_load(index).as_ptr() }
     }
 
-    /// Clears the underlying storage of the entry at the given index.
+    /// Pushes all mutated cached entries to the contract storage under the
+    /// given offset key and returns the number of written storage cells.
     ///
-    /// # Safety
+    /// # Note
     ///
-    /// For performance reasons this does not synchronize the lazy index map's
-    /// memory-side cache which invalidates future accesses the cleared entry.
-    /// Care should be taken when using this API.
+    /// Afterwards all cached entries are marked as preserved. This is what
+    /// [`SpreadLayout::push_spread`] does internally and mainly useful to
+    /// observe the amount of storage writes.
+    pub fn flush_to(&self, offset_key: Key) -> usize {
+        self.flush_to_with(offset_key, |_| ())
+    }
+
+    /// Pushes all mutated cached entries to the contract storage under the
+    /// given offset key and calls `on_write` with the key of every written entry.
     ///
-    /// The general use of this API is to streamline `Drop` implementations of
-    /// high-level abstractions that build upon this low-level data strcuture.
-    pub fn clear_packed_at<Q>(&self, index: &Q)
+    /// Returns the number of written storage cells.
+    fn flush_to_with<F>(&self, offset_key: Key, mut on_write: F) -> usize
     where
-        K: Borrow<Q>,
-        V: PackedLayout,
-        Q: Ord + scale::Encode + ToOwned<Owned = K>,
+        F: FnMut(&K),
     {
-        let root_key = self.key_at(index).expect("cannot clear in lazy state");
-        if <V as SpreadLayout>::REQUIRES_DEEP_CLEAN_UP {
-            // We need to load the entity before we remove its associated contract storage
-            // because it requires a deep clean-up which propagates clearing to its fields,
-            // for example in the case of `T` being a `storage::Box`.
-            let entity = self.get(index).expect("cannot clear a non existing entity");
-            clear_packed_root::<V>(&entity, &root_key);
-        } else {
-            // The type does not require deep clean-up so we can simply clean-up
-            // its associated storage cell and be done without having to load it first.
-            ink_env::clear_contract_storage(&root_key);
+        let mut written = 0;
+        for (index, entry) in self.entries().iter() {
+            if entry.state().is_mutated() {
+                let root_key = self.to_offset_key(&offset_key, index);
+                entry.push_packed_root(&root_key);
+                on_write(index);
+                written += 1;
+            }
         }
+        written
     }
 
-    /// Returns a shared reference to the value associated with the given key if any.
+    /// Pushes the lazy hash map to the contract storage at the given key pointer
+    /// and calls `on_write` with the key of every entry that is written.
     ///
-    /// # Panics
+    /// # Note
+    ///
+    /// Behaves the same as [`SpreadLayout::push_spread`] which uses this with
+    /// a no-op callback. Useful for off-chain tooling to show the progress of
+    /// pushing very large maps or to collect the keys of the written entries.
+    pub fn push_spread_with<F>(&self, ptr: &mut KeyPtr, on_write: F)
+    where
+        F: FnMut(&K),
+    {
+        let offset_key = ExtKeyPtr::next_for::<Self>(ptr);
+        self.salt.push_salt(offset_key);
+        self.flush_to_with(*offset_key, on_write);
+    }
+
+    /// Moves the cached entries of the lazy hash map to the given new offset key.
+    ///
+    /// Returns the lazy hash map bound to `new_key`. All of its cached entries
+    /// are marked as mutated so that they are written to the new offset upon
+    /// the next push. Their storage cells at the old offset key are cleared
+    /// right away, just like the salt of a salted lazy hash map.
+    ///
+    /// # Note
+    ///
+    /// Only cached entries are moved. Entries that have not been loaded are
+    /// not moved. They remain at the old offset key and are unreachable from
+    /// the returned lazy hash map, so load all entries that you want to keep
+    /// before calling this.
+    ///
+    /// Like every other lazy hash map the returned one is pushed to the key
+    /// provided by the key pointer. It therefore has to be pushed and later
+    /// pulled through a key pointer that yields `new_key`.
+    pub fn rebase(self, new_key: Key) -> Self {
+        if let Some(old_key) = &self.key {
+            for index in self.entries().keys() {
+                ink_env::clear_contract_storage(&self.to_offset_key(old_key, index));
+            }
+            self.salt.clear_salt(old_key);
+        }
+        for entry in self.entries().values() {
+            entry.replace_state(EntryState::Mutated);
+        }
+        Self {
+            key: Some(new_key),
+            ..self
+        }
+    }
+
+    /// Clears the underlying storage of the entry at the given index.
+    ///
+    /// # Safety
+    ///
+    /// For performance reasons this does not synchronize the lazy index map's
+    /// memory-side cache which invalidates future accesses the cleared entry.
+    /// Care should be taken when using this API.
+    ///
+    /// The general use of this API is to streamline `Drop` implementations of
+    /// high-level abstractions that build upon this low-level data strcuture.
+    pub fn clear_packed_at<Q>(&self, index: &Q)
+    where
+        K: Borrow<Q>,
+        V: PackedLayout,
+        Q: Ord + scale::Encode + ToOwned<Owned = K>,
+    {
+        let root_key = self.key_at(index).expect("cannot clear in lazy state");
+        if <V as SpreadLayout>::REQUIRES_DEEP_CLEAN_UP {
+            // We need to load the entity before we remove its associated contract storage
+            // because it requires a deep clean-up which propagates clearing to its fields,
+            // for example in the case of `T` being a `storage::Box`.
+            let entity = self.get(index).expect("cannot clear a non existing entity");
+            clear_packed_root::<V>(&entity, &root_key);
+        } else {
+            // The type does not require deep clean-up so we can simply clean-up
+            // its associated storage cell and be done without having to load it first.
+            ink_env::clear_contract_storage(&root_key);
+        }
+    }
+
+    /// Returns a shared reference to the value associated with the given key if any.
+    ///
+    /// # Panics
     ///
     /// - If the lazy chunk is in an invalid state that forbids interaction.
     /// - If the decoding of the element at the given index failed.
@@ -662,6 +1283,51 @@ where
         unsafe { &*self.lazily_load(index).as_ptr() }.value().into()
     }
 
+    /// Returns an iterator over the given candidate keys that yields the keys
+    /// and values of all candidates that have an associated value.
+    ///
+    /// # Note
+    ///
+    /// Since the lazy hash map cannot enumerate the keys stored in the contract
+    /// storage the caller has to provide them, e.g. from an external index.
+    /// Every candidate key is lazily loaded upon iteration.
+    ///
+    /// # Panics
+    ///
+    /// - If the lazy chunk is in an invalid state that forbids interaction.
+    /// - If the decoding of one of the elements failed.
+    pub fn iter_over<'a, I, Q>(&'a self, keys: I) -> impl Iterator<Item = (K, &'a V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + scale::Encode + ToOwned<Owned = K> + 'a,
+        I: IntoIterator<Item = &'a Q>,
+        I::IntoIter: 'a,
+    {
+        keys.into_iter()
+            .filter_map(move |key| self.get(key).map(|value| (key.to_owned(), value)))
+    }
+
+    /// Applies `f` to the value associated with the given key if any and
+    /// returns the result.
+    ///
+    /// # Note
+    ///
+    /// The value is loaded at most once and the caller does not have to hold
+    /// on to the borrow of the value.
+    ///
+    /// # Panics
+    ///
+    /// - If the lazy chunk is in an invalid state that forbids interaction.
+    /// - If the decoding of the element at the given index failed.
+    pub fn get_as<Q, U, F>(&self, index: &Q, f: F) -> Option<U>
+    where
+        K: Borrow<Q>,
+        Q: Ord + scale::Encode + ToOwned<Owned = K>,
+        F: FnOnce(&V) -> U,
+    {
+        self.get(index).map(f)
+    }
+
     /// Returns an exclusive reference to the value associated with the given key if any.
     ///
     /// # Panics
@@ -695,6 +1361,106 @@ where
         self.lazily_load_mut(key).put(new_value)
     }
 
+    /// Removes the values associated with all given keys and returns the
+    /// number of values that have actually been removed.
+    ///
+    /// # Note
+    ///
+    /// Keys without an associated value are not counted and do not need to
+    /// be synchronized with the contract storage afterwards.
+    ///
+    /// # Panics
+    ///
+    /// - If the lazy hashmap is in an invalid state that forbids interaction.
+    /// - If the decoding of one of the removed elements failed.
+    pub fn remove_all<'a, Q, I>(&mut self, keys: I) -> usize
+    where
+        K: Borrow<Q>,
+        Q: 'a + Ord + scale::Encode + ToOwned<Owned = K>,
+        I: IntoIterator<Item = &'a Q>,
+    {
+        let mut removed = 0;
+        for key in keys {
+            if self.put_get(key, None).is_some() {
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// Returns an exclusive reference to the value associated with the given key.
+    ///
+    /// Inserts the default value if there is no value associated with the key, yet.
+    ///
+    /// # Panics
+    ///
+    /// - If the lazy hashmap is in an invalid state that forbids interaction.
+    /// - If the decoding of the element at the given key failed.
+    pub fn get_or_insert_default<Q>(&mut self, key: &Q) -> &mut V
+    where
+        K: Borrow<Q>,
+        Q: Ord + scale::Encode + ToOwned<Owned = K>,
+        V: Default,
+    {
+        let entry = self.lazily_load_mut(key);
+        if entry.value().is_none() {
+            entry.put(Some(<V as Default>::default()));
+        }
+        entry
+            .value_mut()
+            .as_mut()
+            .expect("a value has been inserted if it was missing; qed")
+    }
+
+    /// Returns an exclusive reference to the value associated with the given key.
+    ///
+    /// Inserts the value returned by `f` if there is no value associated with
+    /// the key, yet. If `f` fails its error is returned and the cache is left
+    /// unchanged.
+    ///
+    /// # Panics
+    ///
+    /// - If the lazy hashmap is in an invalid state that forbids interaction.
+    /// - If the decoding of the element at the given key failed.
+    pub fn get_or_try_insert_with<Q, F, E>(&mut self, key: &Q, f: F) -> Result<&mut V, E>
+    where
+        K: Borrow<Q>,
+        Q: Ord + scale::Encode + ToOwned<Owned = K>,
+        F: FnOnce() -> Result<V, E>,
+    {
+        // Nothing is inserted into the cache before `f` succeeded.
+        let has_value = match self.entries().get(key) {
+            Some(entry) => {
+                #[cfg(feature = "lazy-stats")]
+                self.record_cache_access(true);
+                entry.value().is_some()
+            }
+            None => {
+                let value = self.key_at(key).and_then(|key| {
+                    #[cfg(feature = "lazy-stats")]
+                    self.record_cache_access(false);
+                    pull_packed_root_opt::<V>(&key)
+                });
+                let has_value = value.is_some();
+                if has_value {
+                    self.entries_mut().insert(
+                        key.to_owned(),
+                        Box::new(StorageEntry::new(value, EntryState::Preserved)),
+                    );
+                }
+                has_value
+            }
+        };
+        if !has_value {
+            self.put(key.to_owned(), Some(f()?));
+        }
+        Ok(self
+            .entries_mut()
+            .get_mut(key)
+            .and_then(|entry| entry.value_mut().as_mut())
+            .expect("a value has been inserted if it was missing; qed"))
+    }
+
     /// Swaps the values at entries with associated keys `x` and `y`.
     ///
     /// This operation tries to be as efficient as possible and reuse allocations.
@@ -733,6 +1499,34 @@ where
         loaded_y.replace_state(EntryState::Mutated);
         core::mem::swap(loaded_x.value_mut(), loaded_y.value_mut());
     }
+
+    /// Returns exclusive references to the values associated with the keys
+    /// `x` and `y` if any.
+    ///
+    /// # Panics
+    ///
+    /// - If `x` and `y` are equal.
+    /// - If the lazy hashmap is in an invalid state that forbids interaction.
+    /// - If the decoding of one of the elements failed.
+    pub fn get2_mut<Q1, Q2>(&mut self, x: &Q1, y: &Q2) -> (Option<&mut V>, Option<&mut V>)
+    where
+        K: Borrow<Q1> + Borrow<Q2>,
+        Q1: Ord + PartialEq<Q2> + scale::Encode + ToOwned<Owned = K>,
+        Q2: Ord + PartialEq<Q1> + scale::Encode + ToOwned<Owned = K>,
+    {
+        assert!(x != y, "encountered equal keys for get2_mut");
+        let (loaded_x, loaded_y) =
+            // SAFETY: The loaded `x` and `y` entries are distinct from each
+            //         other guaranteed by the previous check. Also `lazily_load`
+            //         guarantees to return a pointer to a pinned entity
+            //         so that the returned references do not conflict with
+            //         each other.
+            unsafe { (
+                &mut *self.lazily_load(x).as_ptr(),
+                &mut *self.lazily_load(y).as_ptr(),
+            ) };
+        (loaded_x.value_mut().into(), loaded_y.value_mut().into())
+    }
 }
 
 impl<'a, K, V> Entry<'a, K, V>
@@ -969,11 +1763,15 @@ where
 #[cfg(test)]
 mod tests {
     use super::{
+        CacheClass,
         EntryState,
         LazyHashMap,
+        MaxEntriesExceeded,
+        Salted,
         StorageEntry,
     };
     use crate::traits::{
+        pull_packed_root_opt,
         KeyPtr,
         SpreadLayout,
     };
@@ -981,6 +1779,7 @@ mod tests {
         Blake2x256,
         Sha2x256,
     };
+    use ink_prelude::collections::BTreeMap;
     use ink_primitives::Key;
 
     /// Asserts that the cached entries of the given `imap` is equal to the `expected` slice.
@@ -1003,6 +1802,19 @@ mod tests {
         <LazyHashMap<i32, u8, Blake2x256>>::new()
     }
 
+    #[test]
+    fn default_hasher_works() {
+        let mut hmap = <LazyHashMap<i32, u8>>::new();
+        hmap.put(1, Some(b'A'));
+        assert_eq!(hmap.put_get(&2, Some(b'B')), None);
+        assert_eq!(hmap.get(&1), Some(&b'A'));
+        assert_eq!(hmap.get(&2), Some(&b'B'));
+        // The default hasher is the same as the explicitly named one.
+        let explicit = <LazyHashMap<i32, u8, Blake2x256>>::lazy(Key::from([0x42; 32]));
+        let implicit = <LazyHashMap<i32, u8>>::lazy(Key::from([0x42; 32]));
+        assert_eq!(explicit.key_at(&1), implicit.key_at(&1));
+    }
+
     #[test]
     fn new_works() {
         let hmap = new_hmap();
@@ -1077,6 +1889,90 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(feature = "debug-collisions")]
+    fn derive_same_offset_key_works() {
+        let hmap = <LazyHashMap<i32, u8, Blake2x256>>::lazy(Key::from([0x42; 32]));
+        // Deriving the offset key of the same key twice is no collision.
+        assert_eq!(hmap.key_at(&1), hmap.key_at(&1));
+        assert_ne!(hmap.key_at(&1), hmap.key_at(&2));
+    }
+
+    #[test]
+    #[cfg(feature = "debug-collisions")]
+    #[should_panic(expected = "encountered offset key collision for distinct keys")]
+    fn offset_key_collision_is_detected() {
+        let hmap = new_hmap();
+        // Pretend that both keys were hashed to the same offset key.
+        let offset_key = Key::from([0x00; 32]);
+        hmap.track_offset_key(&offset_key, scale::Encode::encode(&1));
+        hmap.track_offset_key(&offset_key, scale::Encode::encode(&1));
+        hmap.track_offset_key(&offset_key, scale::Encode::encode(&2));
+    }
+
+    #[test]
+    fn with_salt_works() {
+        let key = Key::from([0x42; 32]);
+        let salted = |salt| {
+            let mut hmap = <LazyHashMap<i32, u8, Blake2x256, Salted>>::with_salt(salt);
+            hmap.key = Some(key);
+            hmap
+        };
+        let unsalted = <LazyHashMap<i32, u8, Blake2x256>>::lazy(key);
+        let salted_a = salted([0x01; 16]);
+        let salted_b = salted([0x02; 16]);
+        // Different salts map the same key to different offset keys.
+        assert_ne!(salted_a.key_at(&1), salted_b.key_at(&1));
+        assert_ne!(salted_a.key_at(&1), unsalted.key_at(&1));
+        assert_ne!(salted_b.key_at(&1), unsalted.key_at(&1));
+        // The same salt always yields the same offset keys.
+        assert_eq!(salted_a.key_at(&1), salted([0x01; 16]).key_at(&1));
+        assert_ne!(salted_a.key_at(&1), salted_a.key_at(&2));
+    }
+
+    #[test]
+    fn salted_push_pull_works() -> ink_env::Result<()> {
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+            let root_key = Key::from([0x42; 32]);
+            let mut hmap =
+                <LazyHashMap<i32, u8, Blake2x256, Salted>>::with_salt([0x01; 16]);
+            hmap.put(1, Some(b'A'));
+            hmap.put(2, Some(b'B'));
+            SpreadLayout::push_spread(&hmap, &mut KeyPtr::from(root_key));
+            assert_eq!(
+                pull_packed_root_opt::<[u8; 16]>(&root_key),
+                Some([0x01; 16])
+            );
+            // The pulled lazy hash map restores the salt and finds the entries.
+            let mut pulled =
+                <LazyHashMap<i32, u8, Blake2x256, Salted> as SpreadLayout>::pull_spread(
+                    &mut KeyPtr::from(root_key),
+                );
+            assert_eq!(pulled.salt.salt, [0x01; 16]);
+            assert_eq!(pulled.get(&1), Some(&b'A'));
+            assert_eq!(pulled.get(&2), Some(&b'B'));
+            assert_eq!(pulled.get(&3), None);
+            // The salt is not written again since it is already stored.
+            ink_env::clear_contract_storage(&root_key);
+            pulled.put(3, Some(b'C'));
+            SpreadLayout::push_spread(&pulled, &mut KeyPtr::from(root_key));
+            assert_eq!(pull_packed_root_opt::<[u8; 16]>(&root_key), None);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn unsalted_push_does_not_write_offset_key() -> ink_env::Result<()> {
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+            let root_key = Key::from([0x42; 32]);
+            let mut hmap = new_hmap();
+            hmap.put(1, Some(b'A'));
+            SpreadLayout::push_spread(&hmap, &mut KeyPtr::from(root_key));
+            assert_eq!(pull_packed_root_opt::<[u8; 16]>(&root_key), None);
+            Ok(())
+        })
+    }
+
     #[test]
     fn put_get_works() {
         let mut hmap = new_hmap();
@@ -1165,6 +2061,30 @@ mod tests {
         assert_eq!(hmap.get_mut(&5), None);
     }
 
+    #[test]
+    fn remove_all_works() {
+        let mut hmap = new_hmap();
+        hmap.put(1, Some(b'A'));
+        hmap.put(2, Some(b'B'));
+        hmap.put(3, Some(b'C'));
+        // Remove a mix of present and absent keys.
+        assert_eq!(hmap.remove_all(&[1, 3, 4, 5]), 2);
+        assert_cached_entries(
+            &hmap,
+            &[
+                (1, StorageEntry::new(None, EntryState::Mutated)),
+                (2, StorageEntry::new(Some(b'B'), EntryState::Mutated)),
+                (3, StorageEntry::new(None, EntryState::Mutated)),
+                (4, StorageEntry::new(None, EntryState::Preserved)),
+                (5, StorageEntry::new(None, EntryState::Preserved)),
+            ],
+        );
+        // Removing already removed keys removes nothing.
+        assert_eq!(hmap.remove_all(&[1, 3]), 0);
+        assert_eq!(hmap.remove_all(vec![&2]), 1);
+        assert_eq!(hmap.get(&2), None);
+    }
+
     #[test]
     fn put_works() {
         let mut hmap = new_hmap();
@@ -1197,6 +2117,375 @@ mod tests {
         );
     }
 
+    #[test]
+    fn get_or_insert_default_works() {
+        let mut hmap = <LazyHashMap<String, u32, Blake2x256>>::new();
+        let words = ["foo", "bar", "foo", "baz", "foo", "bar"];
+        for word in words.iter() {
+            *hmap.get_or_insert_default(&word.to_string()) += 1;
+        }
+        assert_eq!(hmap.get(&"foo".to_string()), Some(&3));
+        assert_eq!(hmap.get(&"bar".to_string()), Some(&2));
+        assert_eq!(hmap.get(&"baz".to_string()), Some(&1));
+        assert_eq!(hmap.get(&"qux".to_string()), None);
+        assert_eq!(
+            hmap.entries().get("qux").map(|entry| &**entry),
+            Some(&StorageEntry::new(None, EntryState::Preserved)),
+        );
+        // Inserted defaults are marked as mutated so that they are pushed.
+        assert_eq!(hmap.get_or_insert_default(&"qux".to_string()), &mut 0);
+        assert_eq!(
+            hmap.entries().get("qux").map(|entry| &**entry),
+            Some(&StorageEntry::new(Some(0), EntryState::Mutated)),
+        );
+    }
+
+    #[test]
+    fn get_or_try_insert_with_works() {
+        let mut hmap = new_hmap();
+        hmap.put(1, Some(b'A'));
+        // Existing values are returned without calling the initializer.
+        assert_eq!(
+            hmap.get_or_try_insert_with(&1, || -> Result<u8, ()> {
+                panic!("unexpected initialization")
+            }),
+            Ok(&mut b'A')
+        );
+        // Missing values are inserted and marked as mutated.
+        assert_eq!(
+            hmap.get_or_try_insert_with(&2, || Ok::<_, ()>(b'B')),
+            Ok(&mut b'B')
+        );
+        let expected = [
+            (1, StorageEntry::new(Some(b'A'), EntryState::Mutated)),
+            (2, StorageEntry::new(Some(b'B'), EntryState::Mutated)),
+        ];
+        assert_cached_entries(&hmap, &expected);
+        // Failing initializers leave the cache unchanged and propagate their error.
+        assert_eq!(
+            hmap.get_or_try_insert_with(&3, || Err("invalid value")),
+            Err("invalid value")
+        );
+        assert_cached_entries(&hmap, &expected);
+    }
+
+    #[test]
+    fn try_put_works() {
+        let mut hmap = <LazyHashMap<i32, u8, Blake2x256>>::with_max_entries(2);
+        assert_eq!(hmap.try_put(1, Some(b'A')), Ok(()));
+        assert_eq!(hmap.try_put(2, Some(b'B')), Ok(()));
+        assert_eq!(hmap.try_put(3, Some(b'C')), Err(MaxEntriesExceeded));
+        // Overwriting cached entries does not grow the cache.
+        assert_eq!(hmap.try_put(2, None), Ok(()));
+        assert_cached_entries(
+            &hmap,
+            &[
+                (1, StorageEntry::new(Some(b'A'), EntryState::Mutated)),
+                (2, StorageEntry::new(None, EntryState::Mutated)),
+            ],
+        );
+        // Without a maximum the cache may grow unbounded.
+        let mut hmap = new_hmap();
+        for i in 0..10 {
+            assert_eq!(hmap.try_put(i, Some(b'A')), Ok(()));
+        }
+        assert_eq!(hmap.len_cached_entries(), 10);
+    }
+
+    #[test]
+    fn get2_mut_works() {
+        let mut hmap = new_hmap();
+        hmap.put(1, Some(b'A'));
+        hmap.put(2, Some(b'B'));
+        // Both keys are present.
+        let (a, b) = hmap.get2_mut(&1, &2);
+        core::mem::swap(a.unwrap(), b.unwrap());
+        assert_eq!(hmap.get(&1), Some(&b'B'));
+        assert_eq!(hmap.get(&2), Some(&b'A'));
+        // Only one of the keys is present.
+        let (a, b) = hmap.get2_mut(&1, &3);
+        assert_eq!(b, None);
+        *a.unwrap() = b'C';
+        assert_cached_entries(
+            &hmap,
+            &[
+                (1, StorageEntry::new(Some(b'C'), EntryState::Mutated)),
+                (2, StorageEntry::new(Some(b'A'), EntryState::Mutated)),
+                (3, StorageEntry::new(None, EntryState::Preserved)),
+            ],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "encountered equal keys for get2_mut")]
+    fn get2_mut_equal_keys_fails() {
+        let mut hmap = new_hmap();
+        hmap.put(1, Some(b'A'));
+        let _ = hmap.get2_mut(&1, &1);
+    }
+
+    #[test]
+    fn to_btreemap_works() -> ink_env::Result<()> {
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+            let root_key = Key::from([0x42; 32]);
+            let mut hmap = new_hmap();
+            hmap.put(1, Some(b'A'));
+            hmap.put(2, Some(b'B'));
+            hmap.put(3, Some(b'C'));
+            hmap.flush_to(root_key);
+            let mut hmap =
+                <LazyHashMap<i32, u8, Blake2x256> as SpreadLayout>::pull_spread(
+                    &mut KeyPtr::from(root_key),
+                );
+            // Nothing has been loaded from the storage so far.
+            assert!(hmap.to_btreemap().is_empty());
+            assert_eq!(hmap.get(&1), Some(&b'A'));
+            assert_eq!(hmap.get(&3), Some(&b'C'));
+            assert_eq!(hmap.get(&4), None);
+            hmap.put(5, Some(b'E'));
+            let expected = [(1, b'A'), (3, b'C'), (5, b'E')]
+                .iter()
+                .copied()
+                .collect::<BTreeMap<_, _>>();
+            assert_eq!(hmap.to_btreemap(), expected);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn export_import_cache_works() -> ink_env::Result<()> {
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+            let mut hmap = new_hmap();
+            hmap.put(1, Some(b'A'));
+            hmap.put(2, Some(b'B'));
+            hmap.flush_to(Key::from([0x42; 32]));
+            hmap.put(2, None);
+            hmap.put(3, Some(b'C'));
+            assert_eq!(hmap.get(&4), None);
+            let exported = hmap.export_cache();
+            let mut imported = new_hmap();
+            imported.put(5, Some(b'E'));
+            assert_eq!(imported.import_cache(&exported), Ok(()));
+            assert_cached_entries(
+                &imported,
+                &[
+                    (1, StorageEntry::new(Some(b'A'), EntryState::Preserved)),
+                    (2, StorageEntry::new(None, EntryState::Mutated)),
+                    (3, StorageEntry::new(Some(b'C'), EntryState::Mutated)),
+                    (4, StorageEntry::new(None, EntryState::Preserved)),
+                ],
+            );
+            // Invalid encodings leave the cached entries unchanged.
+            assert!(imported.import_cache(&exported[1..]).is_err());
+            assert_eq!(imported.export_cache(), exported);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn export_import_mutated_cache_works() -> ink_env::Result<()> {
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+            let mut hmap = new_hmap();
+            hmap.put(1, Some(b'A'));
+            hmap.put(2, Some(b'B'));
+            hmap.put(3, None);
+            let mut imported = new_hmap();
+            assert_eq!(imported.import_cache(&hmap.export_cache()), Ok(()));
+            assert_cached_entries(
+                &imported,
+                &[
+                    (1, StorageEntry::new(Some(b'A'), EntryState::Mutated)),
+                    (2, StorageEntry::new(Some(b'B'), EntryState::Mutated)),
+                    (3, StorageEntry::new(None, EntryState::Mutated)),
+                ],
+            );
+            // The imported mutated entries are written upon the next push.
+            let root_key = Key::from([0x42; 32]);
+            assert_eq!(imported.flush_to(root_key), 3);
+            let pulled = <LazyHashMap<i32, u8, Blake2x256> as SpreadLayout>::pull_spread(
+                &mut KeyPtr::from(root_key),
+            );
+            assert_eq!(pulled.get(&1), Some(&b'A'));
+            assert_eq!(pulled.get(&2), Some(&b'B'));
+            assert_eq!(pulled.get(&3), None);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn apply_works() {
+        let changes = vec![
+            (1, Some(b'A')),
+            (2, Some(b'B')),
+            (3, Some(b'C')),
+            (2, None),
+            (1, Some(b'D')),
+        ];
+        // Record the state of a map that has been mutated step by step.
+        let mut recorded = new_hmap();
+        for (key, new_value) in changes.iter().copied() {
+            recorded.put(key, new_value);
+        }
+        // Applying the changeset reproduces the exact same state.
+        let mut hmap = new_hmap();
+        hmap.apply(changes);
+        assert_cached_entries(
+            &hmap,
+            &[
+                (1, StorageEntry::new(Some(b'D'), EntryState::Mutated)),
+                (2, StorageEntry::new(None, EntryState::Mutated)),
+                (3, StorageEntry::new(Some(b'C'), EntryState::Mutated)),
+            ],
+        );
+        assert_eq!(hmap.to_btreemap(), recorded.to_btreemap());
+        assert_eq!(hmap.len_cached_entries(), recorded.len_cached_entries());
+    }
+
+    #[test]
+    fn cache_breakdown_works() {
+        let mut hmap = new_hmap();
+        assert_eq!(hmap.cache_breakdown(), (0, 0));
+        // Inserted entries are live.
+        hmap.put(1, Some(b'A'));
+        hmap.put(2, Some(b'B'));
+        hmap.put(3, Some(b'C'));
+        assert_eq!(hmap.cache_breakdown(), (3, 0));
+        // Removed entries become tombstones.
+        assert_eq!(hmap.put_get(&2, None), Some(b'B'));
+        assert_eq!(hmap.cache_breakdown(), (2, 1));
+        // Looking up entries that do not exist caches tombstones as well.
+        assert_eq!(hmap.get(&4), None);
+        assert_eq!(hmap.get(&5), None);
+        assert_eq!(hmap.cache_breakdown(), (2, 3));
+        assert_eq!(hmap.len_cached_entries(), 5);
+    }
+
+    #[test]
+    fn update_all_works() -> ink_env::Result<()> {
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+            let mut hmap = new_hmap();
+            hmap.put(1, Some(b'A'));
+            hmap.put(2, Some(b'B'));
+            hmap.put(3, None);
+            assert_eq!(hmap.get(&4), None);
+            // Push the entries so that they are no longer marked as mutated.
+            hmap.flush_to(Key::from([0x42; 32]));
+            let mut visited = Vec::new();
+            hmap.update_all(|key, value| {
+                visited.push(*key);
+                *value *= 2;
+            });
+            // Only the entries holding a value are visited.
+            assert_eq!(visited, vec![1, 2]);
+            assert_cached_entries(
+                &hmap,
+                &[
+                    (1, StorageEntry::new(Some(b'A' * 2), EntryState::Mutated)),
+                    (2, StorageEntry::new(Some(b'B' * 2), EntryState::Mutated)),
+                    (3, StorageEntry::new(None, EntryState::Preserved)),
+                    (4, StorageEntry::new(None, EntryState::Preserved)),
+                ],
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn first_last_cached_works() {
+        let mut hmap = new_hmap();
+        assert_eq!(hmap.first_cached(), None);
+        assert_eq!(hmap.last_cached(), None);
+        for (key, value) in [(1, b'A'), (3, b'C'), (5, b'E'), (7, b'G')].iter() {
+            hmap.put(*key, Some(*value));
+        }
+        assert_eq!(hmap.first_cached(), Some((&1, &b'A')));
+        assert_eq!(hmap.last_cached(), Some((&7, &b'G')));
+        // Removed entries and looked up but missing entries are skipped.
+        hmap.put(1, None);
+        hmap.put(7, None);
+        assert_eq!(hmap.get(&0), None);
+        assert_eq!(hmap.get(&9), None);
+        assert_eq!(hmap.first_cached(), Some((&3, &b'C')));
+        assert_eq!(hmap.last_cached(), Some((&5, &b'E')));
+        hmap.put(3, None);
+        hmap.put(5, None);
+        assert_eq!(hmap.first_cached(), None);
+        assert_eq!(hmap.last_cached(), None);
+    }
+
+    #[test]
+    fn peek_works() {
+        let mut hmap = <LazyHashMap<String, u8, Blake2x256>>::new();
+        hmap.put("cached".to_string(), Some(b'A'));
+        // Lookups with borrowed `str` keys only query the cache.
+        assert_eq!(hmap.peek("cached"), Some(&b'A'));
+        assert!(hmap.contains_only_cached("cached"));
+        assert_eq!(hmap.peek("uncached"), None);
+        assert!(!hmap.contains_only_cached("uncached"));
+        // Cache misses do not insert new entries into the cache.
+        assert_eq!(hmap.len_cached_entries(), 1);
+        // Removed entries are still cached but no longer contain a value.
+        hmap.put("cached".to_string(), None);
+        assert_eq!(hmap.peek("cached"), None);
+        assert!(!hmap.contains_only_cached("cached"));
+    }
+
+    #[test]
+    fn classify_works() -> ink_env::Result<()> {
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+            let root_key = Key::from([0x42; 32]);
+            let mut hmap = new_hmap();
+            hmap.put(1, Some(b'A'));
+            hmap.flush_to(root_key);
+            let hmap = <LazyHashMap<i32, u8, Blake2x256> as SpreadLayout>::pull_spread(
+                &mut KeyPtr::from(root_key),
+            );
+            // Classifying does not load from the contract storage.
+            assert_eq!(hmap.classify(&1), CacheClass::UncachedUnknown);
+            assert_eq!(hmap.classify(&2), CacheClass::UncachedUnknown);
+            assert_eq!(hmap.len_cached_entries(), 0);
+            assert_eq!(hmap.get(&1), Some(&b'A'));
+            assert_eq!(hmap.get(&2), None);
+            assert_eq!(hmap.classify(&1), CacheClass::CachedPresent);
+            assert_eq!(hmap.classify(&2), CacheClass::CachedAbsent);
+            Ok(())
+        })
+    }
+
+    #[test]
+    #[cfg(feature = "lazy-stats")]
+    fn cache_stats_works() -> ink_env::Result<()> {
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+            let mut hmap =
+                <LazyHashMap<i32, u8, Blake2x256>>::lazy(Key::from([0x42; 32]));
+            assert_eq!(hmap.cache_stats(), (0, 0));
+            // The first read misses the cache and the second one hits it.
+            assert_eq!(hmap.get(&1), None);
+            assert_eq!(hmap.cache_stats(), (0, 1));
+            assert_eq!(hmap.get(&1), None);
+            assert_eq!(hmap.cache_stats(), (1, 1));
+            // Putting a value does not load from storage.
+            hmap.put(2, Some(b'B'));
+            assert_eq!(hmap.cache_stats(), (1, 1));
+            assert_eq!(hmap.get(&2), Some(&b'B'));
+            assert_eq!(hmap.cache_stats(), (2, 1));
+            Ok(())
+        })
+    }
+
+    #[test]
+    #[cfg(feature = "lazy-stats")]
+    fn cache_stats_without_key_works() {
+        let mut hmap = new_hmap();
+        // Without a key there is no storage to load from and thus no miss.
+        assert_eq!(hmap.get(&1), None);
+        assert_eq!(*hmap.entry(2).or_insert(b'B'), b'B');
+        assert_eq!(hmap.cache_stats(), (0, 0));
+        assert_eq!(hmap.get(&1), None);
+        assert_eq!(hmap.get(&2), Some(&b'B'));
+        assert_eq!(hmap.cache_stats(), (2, 0));
+    }
+
     #[test]
     fn swap_works() {
         let mut hmap = new_hmap();
@@ -1270,6 +2559,192 @@ mod tests {
         );
     }
 
+    #[test]
+    fn flush_to_works() -> ink_env::Result<()> {
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+            let mut hmap = new_hmap();
+            hmap.put(1, Some(b'A'));
+            hmap.put(2, Some(b'B'));
+            hmap.put(3, None);
+            assert_eq!(hmap.put_get(&4, None), None);
+            let root_key = Key::from([0x42; 32]);
+            // Only the 3 mutated entries are written.
+            assert_eq!(hmap.flush_to(root_key), 3);
+            assert_cached_entries(
+                &hmap,
+                &[
+                    (1, StorageEntry::new(Some(b'A'), EntryState::Preserved)),
+                    (2, StorageEntry::new(Some(b'B'), EntryState::Preserved)),
+                    (3, StorageEntry::new(None, EntryState::Preserved)),
+                    (4, StorageEntry::new(None, EntryState::Preserved)),
+                ],
+            );
+            // Flushing again writes nothing.
+            assert_eq!(hmap.flush_to(root_key), 0);
+            // The flushed entries can be loaded from the same offset key.
+            let hmap2 = <LazyHashMap<i32, u8, Blake2x256> as SpreadLayout>::pull_spread(
+                &mut KeyPtr::from(root_key),
+            );
+            assert_eq!(hmap2.get(&1), Some(&b'A'));
+            assert_eq!(hmap2.get(&2), Some(&b'B'));
+            assert_eq!(hmap2.get(&3), None);
+            assert_eq!(hmap2.get(&4), None);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn push_spread_with_works() -> ink_env::Result<()> {
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+            let root_key = Key::from([0x42; 32]);
+            let mut hmap = new_hmap();
+            hmap.put(1, Some(b'A'));
+            hmap.put(2, Some(b'B'));
+            hmap.put(3, None);
+            SpreadLayout::push_spread(&hmap, &mut KeyPtr::from(root_key));
+            // Only the entries that are mutated after the first push are written.
+            hmap.put(2, Some(b'C'));
+            hmap.put(4, Some(b'D'));
+            assert_eq!(hmap.get(&1), Some(&b'A'));
+            let mutated = hmap
+                .entries()
+                .iter()
+                .filter(|(_, entry)| entry.state().is_mutated())
+                .map(|(index, _)| *index)
+                .collect::<Vec<_>>();
+            let mut written = Vec::new();
+            hmap.push_spread_with(&mut KeyPtr::from(root_key), |index| {
+                written.push(*index)
+            });
+            assert_eq!(written, vec![2, 4]);
+            assert_eq!(written, mutated);
+            // The written entries can be loaded from the same key pointer.
+            let hmap2 = <LazyHashMap<i32, u8, Blake2x256> as SpreadLayout>::pull_spread(
+                &mut KeyPtr::from(root_key),
+            );
+            assert_eq!(hmap2.get(&1), Some(&b'A'));
+            assert_eq!(hmap2.get(&2), Some(&b'C'));
+            assert_eq!(hmap2.get(&4), Some(&b'D'));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn rebase_works() -> ink_env::Result<()> {
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+            let old_key = Key::from([0x42; 32]);
+            let new_key = Key::from([0x77; 32]);
+            let mut hmap = new_hmap();
+            hmap.put(1, Some(b'A'));
+            hmap.put(2, Some(b'B'));
+            hmap.put(3, Some(b'C'));
+            hmap.flush_to(old_key);
+            // Load some of the entries from the old region and move them.
+            let hmap = <LazyHashMap<i32, u8, Blake2x256> as SpreadLayout>::pull_spread(
+                &mut KeyPtr::from(old_key),
+            );
+            assert_eq!(hmap.get(&1), Some(&b'A'));
+            assert_eq!(hmap.get(&2), Some(&b'B'));
+            let hmap = hmap.rebase(new_key);
+            assert_eq!(hmap.key(), Some(&new_key));
+            assert_cached_entries(
+                &hmap,
+                &[
+                    (1, StorageEntry::new(Some(b'A'), EntryState::Mutated)),
+                    (2, StorageEntry::new(Some(b'B'), EntryState::Mutated)),
+                ],
+            );
+            // The cached entries have been cleared from the old region while
+            // the entry that has not been loaded remains there.
+            let old = <LazyHashMap<i32, u8, Blake2x256> as SpreadLayout>::pull_spread(
+                &mut KeyPtr::from(old_key),
+            );
+            assert_eq!(old.get(&1), None);
+            assert_eq!(old.get(&2), None);
+            assert_eq!(old.get(&3), Some(&b'C'));
+            // Pushing through a key pointer that yields the new key writes
+            // the moved entries to the new region.
+            let mut ptr = KeyPtr::from(new_key);
+            SpreadLayout::push_spread(&hmap, &mut ptr);
+            assert_eq!(ptr.advance_by(0), &(new_key + 1));
+            let moved = <LazyHashMap<i32, u8, Blake2x256> as SpreadLayout>::pull_spread(
+                &mut KeyPtr::from(new_key),
+            );
+            assert_eq!(moved.get(&1), Some(&b'A'));
+            assert_eq!(moved.get(&2), Some(&b'B'));
+            assert_eq!(moved.get(&3), None);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn rebase_lazy_works() {
+        let mut hmap = new_hmap();
+        hmap.put(1, Some(b'A'));
+        assert_eq!(hmap.key(), None);
+        let new_key = Key::from([0x77; 32]);
+        let hmap = hmap.rebase(new_key);
+        assert_eq!(hmap.key(), Some(&new_key));
+        assert_cached_entries(
+            &hmap,
+            &[(1, StorageEntry::new(Some(b'A'), EntryState::Mutated))],
+        );
+    }
+
+    #[test]
+    fn iter_over_works() -> ink_env::Result<()> {
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+            let root_key = Key::from([0x42; 32]);
+            let mut hmap = new_hmap();
+            hmap.put(1, Some(b'A'));
+            hmap.put(3, Some(b'C'));
+            hmap.put(5, Some(b'E'));
+            hmap.flush_to(root_key);
+            let mut hmap =
+                <LazyHashMap<i32, u8, Blake2x256> as SpreadLayout>::pull_spread(
+                    &mut KeyPtr::from(root_key),
+                );
+            // Cached entries that are not yet pushed are yielded as well.
+            hmap.put(6, Some(b'F'));
+            let candidates = [0, 1, 2, 3, 4, 5, 6];
+            assert_eq!(
+                hmap.iter_over(candidates.iter()).collect::<Vec<_>>(),
+                vec![(1, &b'A'), (3, &b'C'), (5, &b'E'), (6, &b'F')],
+            );
+            assert_eq!(hmap.iter_over([2, 4].iter()).count(), 0);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn get_as_works() -> ink_env::Result<()> {
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+            let root_key = Key::from([0x42; 32]);
+            let mut hmap = <LazyHashMap<i32, (u32, u8), Blake2x256>>::new();
+            hmap.put(1, Some((1337, b'A')));
+            hmap.flush_to(root_key);
+            let contract_account = ink_env::test::get_current_contract_account_id::<
+                ink_env::DefaultEnvironment,
+            >()?;
+            let (base_reads, _) = ink_env::test::get_contract_storage_rw::<
+                ink_env::DefaultEnvironment,
+            >(&contract_account)?;
+            let hmap =
+                <LazyHashMap<i32, (u32, u8), Blake2x256> as SpreadLayout>::pull_spread(
+                    &mut KeyPtr::from(root_key),
+                );
+            assert_eq!(hmap.get_as(&1, |(_, byte)| *byte), Some(b'A'));
+            assert_eq!(hmap.get_as(&1, |(value, _)| *value), Some(1337));
+            assert_eq!(hmap.get_as(&2, |(value, _)| *value), None);
+            // Each of both keys has been loaded from the storage exactly once.
+            let (reads, _) = ink_env::test::get_contract_storage_rw::<
+                ink_env::DefaultEnvironment,
+            >(&contract_account)?;
+            assert_eq!(reads - base_reads, 2);
+            Ok(())
+        })
+    }
+
     #[test]
     fn spread_layout_works() -> ink_env::Result<()> {
         ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {